@@ -7,17 +7,17 @@ use self::mocks::MockStockServiceApi;
 use actix_rt::task::JoinHandle;
 use actix_rt::System;
 use actix_web::dev::Server;
-use anyhow::Error;
+use anyhow::{Context, Error};
 use cart_server::{initialise_tracing, run_server, Configuration};
-use mock_otel_collector::jaeger_models::Span;
-use mock_otel_collector::DetachedMockOtelCollector;
+use mock_otel_collector::jaeger_models::{SnapshotSettings, Trace, TraceAssertion, TraceId};
+use mock_otel_collector::{assert_snapshot, DetachedMockOtelCollector, TraceCompletenessSettings};
 use opentelemetry::global::force_flush_tracer_provider;
-use rctree::Node;
 use std::future::pending;
 use std::net::TcpListener;
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::OnceCell;
 
 static TRACING_INIT: OnceCell<DetachedMockOtelCollector> = OnceCell::const_new();
@@ -151,28 +151,96 @@ impl TestHarness {
         }
     }
 
-    pub async fn check_trace<F>(
+    /// Check a trace against an assertion, such as a `TraceShape` or a closure.
+    /// The trace is first waited for until it is complete, so that the assertion
+    /// is not run against a partially exported trace, and the assertion is then
+    /// retried until it passes or a timeout elapses.
+    ///
+    /// If the trace never passes, it is written to the target directory in the
+    /// Chrome trace event format, for investigation in `ui.perfetto.dev`, and the
+    /// path it was written to is included in the returned error.
+    pub async fn check_trace(
         &self,
-        trace_id: String,
-        check_trace: F,
-    ) -> Result<(), RetryTimeoutError<anyhow::Error>>
-    where
-        F: Fn(Node<Span>) -> Result<(), anyhow::Error>,
-    {
+        trace_id: impl Into<TraceId>,
+        assertion: impl TraceAssertion,
+    ) -> Result<(), RetryTimeoutError<anyhow::Error>> {
+        let trace_id = trace_id.into();
         let timeout = Duration::from_secs(5);
-        retry_until_ok(
-            || async {
-                // Since our telemetry state is global and shared between our
-                // test and our server, we can cheat a little here and force
-                // `opentelemetry` to flush any pending traces
-                force_flush_tracer_provider();
-                let trace = self.mock_otel_collector.get_trace(&trace_id).await?;
-                check_trace(trace)
-            },
-            timeout,
-            timeout,
-            Duration::from_millis(100),
-        )
+        let started = Instant::now();
+
+        // Since our telemetry state is global and shared between our
+        // test and our server, we can cheat a little here and force
+        // `opentelemetry` to flush any pending traces
+        force_flush_tracer_provider();
+        // Spend at most half of the budget waiting for the trace to be complete,
+        // leaving the rest for retrying the assertion, in case spans arrive after
+        // the trace first appears complete.
+        let completeness = TraceCompletenessSettings {
+            timeout: timeout / 2,
+            ..TraceCompletenessSettings::default()
+        };
+        let result = match self
+            .mock_otel_collector
+            .wait_for_complete_trace_with(trace_id, &completeness)
+            .await
+        {
+            Err(error) => Err(RetryTimeoutError::NeverSucceeded(error)),
+            Ok(_) => {
+                let remaining = timeout.saturating_sub(started.elapsed());
+                retry_until_ok(
+                    || async {
+                        force_flush_tracer_provider();
+                        let trace = self.mock_otel_collector.get_trace(trace_id).await?;
+                        assertion
+                            .check(&trace)
+                            .with_context(|| format!("Trace did not pass checks:\n{}", trace))
+                    },
+                    remaining,
+                    remaining,
+                    Duration::from_millis(100),
+                )
+                .await
+            }
+        };
+
+        match result {
+            Err(RetryTimeoutError::NeverSucceeded(error)) => {
+                let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+                    .join("traces")
+                    .join(format!("{}.json", trace_id));
+                let written = self
+                    .mock_otel_collector
+                    .write_chrome_trace(trace_id, &path)
+                    .await;
+                let error = match written {
+                    Ok(_) => error.context(format!(
+                        "Wrote trace {} to {}; open it in https://ui.perfetto.dev to investigate",
+                        trace_id,
+                        path.display()
+                    )),
+                    Err(_) => error,
+                };
+                Err(RetryTimeoutError::NeverSucceeded(error))
+            }
+            result => result,
+        }
+    }
+
+    /// Check a trace against the golden snapshot with the given name, stored in
+    /// `tests/all/snapshots`. See [`assert_snapshot`] for how new and changed
+    /// snapshots are handled.
+    pub async fn check_trace_snapshot(
+        &self,
+        trace_id: impl Into<TraceId>,
+        snapshot_name: &str,
+    ) -> Result<(), RetryTimeoutError<anyhow::Error>> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/all/snapshots")
+            .join(format!("{}.snap", snapshot_name));
+        self.check_trace(trace_id, |trace: &Trace| {
+            assert_snapshot(&path, &trace.to_snapshot(&SnapshotSettings::default()))?;
+            Ok(())
+        })
         .await
     }
 }
@@ -1,10 +1,10 @@
 use crate::test_harness::TestHarness;
 use crate::utilities::tracer::Tracer;
-use anyhow::{anyhow, Context};
-use mock_otel_collector::jaeger_models::{Span, TagValue};
+use anyhow::ensure;
+use cart_server::SERVER_NAME;
+use mock_otel_collector::jaeger_models::{SpanShape, SpanStatusCode, Trace, TraceShape};
 use prometheus_parse::Value;
-use rctree::Node;
-use reqwest::StatusCode;
+use reqwest::{StatusCode, Url};
 use uuid::Uuid;
 
 #[actix_rt::test]
@@ -49,28 +49,41 @@ pub async fn add_item_when_stock_for_item_exists_returns_ok() {
     assert_eq!(http_requests_total_sample.value, Value::Counter(1.into()));
 
     test_harness
-        .check_trace(trace_id, |trace| {
-            let stock_check_request_span = trace
-                .descendants()
-                .find(|s| s.borrow().operation_name == format!("GET /stock/{}", &item_id))
-                .ok_or_else(|| anyhow!(r#"No span found for stock check request""#))?;
-
-            check_tag(
-                &stock_check_request_span,
-                "http.method",
-                TagValue::String("GET"),
-            )
-            .context("http.method tag was not correct")?;
+        .check_trace(
+            trace_id,
+            TraceShape::new().contains(
+                SpanShape::new(format!("GET /stock/{}", &item_id))
+                    .tag("http.method", "GET")
+                    .tag("http.status_code", 200),
+            ),
+        )
+        .await
+        .expect("Expected trace was not available within timeout");
 
-            check_tag(
-                &stock_check_request_span,
-                "http.status_code",
-                TagValue::Long(200),
-            )
-            .context("http.status_code tag was not correct")
+    test_harness
+        .check_trace_snapshot(trace_id, "add_item_when_stock_for_item_exists")
+        .await
+        .expect("Trace did not match its snapshot");
+
+    // The mock stock service reports no spans of its own, so it is named after
+    // the client span which called it. `reqwest-tracing` records neither
+    // `peer.service` nor `net.peer.port` on that span, only `http.host`, so the
+    // callee is the bare host of the stock service's URL, without its port.
+    let stock_service_url = Url::parse(&test_harness.config.stock_service_url).unwrap();
+    let stock_service_host = stock_service_url.host_str().unwrap();
+    test_harness
+        .check_trace(trace_id, |trace: &Trace| {
+            let dependencies = trace.dependencies();
+            ensure!(
+                dependencies.callees(SERVER_NAME) == [stock_service_host],
+                "Expected {} to call only the stock service, found:\n{}",
+                SERVER_NAME,
+                dependencies
+            );
+            Ok(())
         })
         .await
-        .expect("Expected trace was not available within timeout");
+        .expect("Service called something other than the stock service");
 }
 
 #[actix_rt::test]
@@ -115,43 +128,20 @@ pub async fn add_item_when_stock_service_is_unavailable_returns_error() {
     assert_eq!(http_requests_total_sample.value, Value::Counter(1.into()));
 
     test_harness
-        .check_trace(trace_id, |trace| {
-            let stock_check_request_span = trace
-                .descendants()
-                .find(|s| s.borrow().operation_name == format!("GET /stock/{}", &item_id))
-                .ok_or_else(|| anyhow!(r#"No span found for stock check request""#))?;
-
-            check_tag(
-                &stock_check_request_span,
-                "http.method",
-                TagValue::String("GET"),
-            )
-            .context("http.method tag was not correct")?;
-
-            check_tag(
-                &stock_check_request_span,
-                "http.status_code",
-                TagValue::Long(500),
-            )
-            .context("http.status_code tag was not correct")
-        })
+        .check_trace(
+            trace_id,
+            TraceShape::new().contains(
+                SpanShape::new(format!("GET /stock/{}", &item_id))
+                    .tag("http.method", "GET")
+                    .tag("http.status_code", 500)
+                    .status(SpanStatusCode::Error),
+            ),
+        )
         .await
         .expect("Expected trace was not available within timeout");
-}
 
-fn check_tag(span: &Node<Span>, key: &str, expected_value: TagValue) -> Result<(), anyhow::Error> {
-    let span_ref = span.borrow();
-    let tag = span_ref
-        .get_tag(key)
-        .ok_or_else(|| anyhow!(format!("No tag with key {} was found", key)))?;
-
-    let value = tag.value().context("Could not interpret tag value")?;
-    if value == expected_value {
-        Ok(())
-    } else {
-        Err(anyhow!(format!(
-            "Tag with key {} was found, but its value was {:?}, not {:?}",
-            key, value, expected_value
-        )))
-    }
+    test_harness
+        .check_trace_snapshot(trace_id, "add_item_when_stock_service_is_unavailable")
+        .await
+        .expect("Trace did not match its snapshot");
 }
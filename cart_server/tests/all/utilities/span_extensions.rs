@@ -1,13 +1,13 @@
-use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::{TraceContextExt, TraceId};
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 pub trait SpanExt {
-    fn otel_trace_id(&self) -> String;
+    fn otel_trace_id(&self) -> TraceId;
 }
 
 impl SpanExt for Span {
-    fn otel_trace_id(&self) -> String {
-        self.context().span().span_context().trace_id().to_hex()
+    fn otel_trace_id(&self) -> TraceId {
+        self.context().span().span_context().trace_id()
     }
 }
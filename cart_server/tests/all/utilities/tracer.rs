@@ -1,5 +1,6 @@
 use std::future::Future;
 
+use opentelemetry::trace::TraceId;
 use tracing::{instrument, Span};
 
 use super::span_extensions::SpanExt;
@@ -11,7 +12,7 @@ impl Tracer {
     // executing the function body and is closed after
     // the function returns.
     #[instrument(skip(f))]
-    pub async fn trace<F, T>(f: F) -> (T, String)
+    pub async fn trace<F, T>(f: F) -> (T, TraceId)
     where
         F: Future<Output = T>,
     {
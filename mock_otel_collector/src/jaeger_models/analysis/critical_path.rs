@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use crate::jaeger_models::{Trace, TraceSpan};
+
+/// The chain of spans which determined a trace's end-to-end duration.
+///
+/// Working backwards from the end of the root span, the critical path follows
+/// whichever child finished last, then whichever child finished last before
+/// that one started, and so on, recursing into each child in turn. Any time not
+/// covered by a child on the path is attributed to the parent itself.
+#[derive(Debug)]
+pub struct CriticalPath<'t> {
+    spans: Vec<CriticalPathSpan<'t>>,
+}
+
+/// A span on the critical path, along with the time on the path attributed to
+/// the span itself rather than to any of its children.
+#[derive(Clone, Copy, Debug)]
+pub struct CriticalPathSpan<'t> {
+    pub span: TraceSpan<'t>,
+    pub self_time: Duration,
+}
+
+impl Trace {
+    /// Compute the [`CriticalPath`] of this trace.
+    pub fn critical_path(&self) -> CriticalPath<'_> {
+        let root = self.root();
+        let mut segments = Vec::new();
+        walk(root, root.start_time, end_time(root), &mut segments);
+
+        // Segments are discovered from the end of the trace backwards.
+        segments.reverse();
+
+        let mut spans: Vec<CriticalPathSpan> = Vec::new();
+        for (span, self_time) in segments {
+            match spans.iter_mut().find(|s| s.span.index() == span.index()) {
+                Some(existing) => existing.self_time += self_time,
+                None => spans.push(CriticalPathSpan { span, self_time }),
+            }
+        }
+
+        CriticalPath { spans }
+    }
+}
+
+impl<'t> CriticalPath<'t> {
+    /// The spans on the critical path, in the order they first appear on it.
+    pub fn spans(&self) -> &[CriticalPathSpan<'t>] {
+        &self.spans
+    }
+
+    /// The total length of the critical path, which is the duration of the root span.
+    pub fn duration(&self) -> Duration {
+        self.spans.iter().map(|s| s.self_time).sum()
+    }
+
+    /// The span on the critical path with the greatest self time.
+    pub fn bottleneck(&self) -> Option<&CriticalPathSpan<'t>> {
+        self.spans.iter().max_by_key(|s| s.self_time)
+    }
+
+    /// The time on the critical path spent within `span`, including time spent in
+    /// any of its descendants.
+    pub fn time_within(&self, span: TraceSpan) -> Duration {
+        self.spans
+            .iter()
+            .filter(|s| {
+                s.span.index() == span.index()
+                    || s.span.ancestors().any(|a| a.index() == span.index())
+            })
+            .map(|s| s.self_time)
+            .sum()
+    }
+
+    /// The fraction, between 0 and 1, of the critical path spent within `span`,
+    /// including time spent in any of its descendants.
+    pub fn fraction_within(&self, span: TraceSpan) -> f64 {
+        let total = self.duration().as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.time_within(span).as_secs_f64() / total
+    }
+}
+
+fn end_time(span: TraceSpan) -> i64 {
+    span.start_time + span.duration.max(0)
+}
+
+/// Walk the critical path of `span`, restricted to the window between
+/// `window_start` and `window_end`, pushing each span's self time segments onto
+/// `segments` from latest to earliest.
+fn walk<'t>(
+    span: TraceSpan<'t>,
+    window_start: i64,
+    window_end: i64,
+    segments: &mut Vec<(TraceSpan<'t>, Duration)>,
+) {
+    let start = span.start_time.max(window_start);
+    let mut cursor = end_time(span).min(window_end);
+
+    while cursor > start {
+        let last_finishing_child = span
+            .children()
+            .filter(|child| child.start_time < cursor && end_time(*child) > start)
+            .max_by_key(|child| (end_time(*child).min(cursor), child.start_time));
+
+        let child = match last_finishing_child {
+            Some(child) => child,
+            None => break,
+        };
+
+        let child_end = end_time(child).min(cursor);
+        if child_end < cursor {
+            segments.push((span, micros(cursor - child_end)));
+        }
+        walk(child, start, child_end, segments);
+        cursor = child.start_time.max(start);
+    }
+
+    if cursor > start {
+        segments.push((span, micros(cursor - start)));
+    }
+}
+
+fn micros(value: i64) -> Duration {
+    Duration::from_micros(value.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{Span, SpanBuilder, TraceId};
+
+    fn span(operation: &str, parent: Option<&Span>, start: i64, duration: u64) -> Span {
+        let builder = SpanBuilder::new(operation)
+            .trace_id(TraceId(1))
+            .start_time(start)
+            .duration(Duration::from_micros(duration));
+        match parent {
+            Some(parent) => builder.child_of(parent),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// A root running from 0 to 100µs, with children given as
+    /// `(operation, start, duration)`.
+    fn trace(children: &[(&str, i64, u64)]) -> Trace {
+        let root = span("root", None, 0, 100);
+        let mut spans: Vec<Span> = children
+            .iter()
+            .map(|(operation, start, duration)| span(operation, Some(&root), *start, *duration))
+            .collect();
+        spans.push(root);
+        Trace::from_spans(spans).unwrap()
+    }
+
+    fn self_times(path: &CriticalPath) -> Vec<(String, u128)> {
+        path.spans()
+            .iter()
+            .map(|s| (s.span.operation_name.clone(), s.self_time.as_micros()))
+            .collect()
+    }
+
+    fn owned(expected: &[(&str, u128)]) -> Vec<(String, u128)> {
+        expected
+            .iter()
+            .map(|(operation, micros)| (operation.to_string(), *micros))
+            .collect()
+    }
+
+    #[test]
+    fn sequential_children_are_all_on_the_path() {
+        let trace = trace(&[("a", 10, 20), ("b", 40, 50)]);
+
+        let path = trace.critical_path();
+
+        assert_eq!(
+            self_times(&path),
+            owned(&[("root", 30), ("a", 20), ("b", 50)])
+        );
+        assert_eq!(path.duration(), Duration::from_micros(100));
+        assert_eq!(path.bottleneck().unwrap().span.operation_name, "b");
+    }
+
+    #[test]
+    fn overlapping_children_only_count_until_the_later_one_starts() {
+        let trace = trace(&[("a", 10, 40), ("b", 30, 50)]);
+
+        let path = trace.critical_path();
+
+        assert_eq!(
+            self_times(&path),
+            owned(&[("root", 30), ("a", 20), ("b", 50)])
+        );
+    }
+
+    #[test]
+    fn children_finishing_before_a_later_sibling_starts_are_left_off() {
+        let trace = trace(&[("a", 10, 60), ("b", 20, 40)]);
+
+        let path = trace.critical_path();
+
+        assert_eq!(self_times(&path), owned(&[("root", 40), ("a", 60)]));
+        let b = trace.find_by_operation("b").unwrap();
+        assert_eq!(path.time_within(b), Duration::ZERO);
+    }
+
+    #[test]
+    fn children_running_past_their_parent_are_clipped() {
+        let trace = trace(&[("a", 50, 100)]);
+
+        let path = trace.critical_path();
+
+        assert_eq!(self_times(&path), owned(&[("root", 50), ("a", 50)]));
+        assert_eq!(path.duration(), Duration::from_micros(100));
+    }
+
+    #[test]
+    fn fractions_include_time_spent_in_descendants() {
+        let root = span("root", None, 0, 100);
+        let child = span("child", Some(&root), 0, 80);
+        let grandchild = span("grandchild", Some(&child), 10, 50);
+        let trace = Trace::from_spans(vec![root, child, grandchild]).unwrap();
+
+        let path = trace.critical_path();
+
+        assert_eq!(path.bottleneck().unwrap().span.operation_name, "grandchild");
+        let fraction =
+            |operation| path.fraction_within(trace.find_by_operation(operation).unwrap());
+        assert_eq!(fraction("root"), 1.0);
+        assert_eq!(fraction("child"), 0.8);
+        assert_eq!(fraction("grandchild"), 0.5);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+
+use crate::jaeger_models::{TagValue, Trace, TraceSpan};
+
+/// Calls made from one service to another, in the JSON format used by Jaeger's
+/// dependencies API, plus a count of the calls which failed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyLink {
+    pub parent: String,
+    pub child: String,
+    pub call_count: u64,
+    pub error_count: u64,
+}
+
+/// The services which called each other in a set of traces.
+///
+/// A link is recorded wherever a span's parent was reported by a different
+/// service. Calls to services which do not report spans of their own, such as
+/// databases or mocks, are recorded from the client spans which made them, if
+/// those spans have no children: the callee is named by the span's `peer.service`
+/// tag, or failing that by its `net.peer.name` or `http.host` tag, followed by
+/// `net.peer.port` if present.
+/// Spans whose process is unknown are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    links: BTreeMap<(String, String), DependencyLink>,
+}
+
+impl Trace {
+    /// Compute the [`DependencyGraph`] of this trace.
+    pub fn dependencies(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        graph.add_trace(self);
+        graph
+    }
+}
+
+impl DependencyGraph {
+    pub fn from_traces<'t>(traces: impl IntoIterator<Item = &'t Trace>) -> Self {
+        let mut graph = Self::default();
+        for trace in traces {
+            graph.add_trace(trace);
+        }
+        graph
+    }
+
+    fn add_trace(&mut self, trace: &Trace) {
+        for span in trace.descendants() {
+            let service = match span.service_name() {
+                Some(service) => service,
+                None => continue,
+            };
+            let is_error = span.status().is_error();
+
+            if let Some(parent_service) = span.parent().and_then(|parent| parent.service_name()) {
+                if parent_service != service {
+                    self.record(parent_service, service, is_error);
+                }
+            }
+
+            let callee_reported = span.children().any(|child| child.process().is_some());
+            if span.span_kind() == Some("client") && !callee_reported {
+                if let Some(peer) = peer_name(span) {
+                    self.record(service, &peer, is_error);
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, parent: &str, child: &str, is_error: bool) {
+        let link = self
+            .links
+            .entry((parent.to_owned(), child.to_owned()))
+            .or_insert_with(|| DependencyLink {
+                parent: parent.to_owned(),
+                child: child.to_owned(),
+                call_count: 0,
+                error_count: 0,
+            });
+        link.call_count += 1;
+        if is_error {
+            link.error_count += 1;
+        }
+    }
+
+    /// Every link, ordered by parent and then child.
+    pub fn links(&self) -> impl Iterator<Item = &DependencyLink> {
+        self.links.values()
+    }
+
+    pub fn link(&self, parent: &str, child: &str) -> Option<&DependencyLink> {
+        self.links.get(&(parent.to_owned(), child.to_owned()))
+    }
+
+    /// The services called by `parent`, in name order.
+    pub fn callees(&self, parent: &str) -> Vec<&str> {
+        self.links()
+            .filter(|link| link.parent == parent)
+            .map(|link| link.child.as_str())
+            .collect()
+    }
+
+    /// The services which called `child`, in name order.
+    pub fn callers(&self, child: &str) -> Vec<&str> {
+        self.links()
+            .filter(|link| link.child == child)
+            .map(|link| link.parent.as_str())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+}
+
+/// Renders one line per link, e.g. `cart_server -> stock_service (3 calls, 1 errors)`.
+impl Display for DependencyGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for link in self.links() {
+            writeln!(
+                f,
+                "{} -> {} ({} calls, {} errors)",
+                link.parent, link.child, link.call_count, link.error_count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of the peer a client span called, read from its tags.
+fn peer_name(span: TraceSpan) -> Option<String> {
+    if let Some(service) = str_tag(span, "peer.service") {
+        return Some(service.to_owned());
+    }
+    let host = str_tag(span, "net.peer.name").or_else(|| str_tag(span, "http.host"))?;
+    let port = span
+        .span()
+        .get_tag("net.peer.port")
+        .and_then(|tag| tag.value().ok());
+    match port {
+        Some(TagValue::Long(port)) => Some(format!("{}:{}", host, port)),
+        Some(TagValue::String(port)) => Some(format!("{}:{}", host, port)),
+        _ => Some(host.to_owned()),
+    }
+}
+
+fn str_tag<'t>(span: TraceSpan<'t>, key: &str) -> Option<&'t str> {
+    match span.span().get_tag(key)?.value().ok()? {
+        TagValue::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{Process, SpanBuilder};
+
+    fn process(service_name: &str) -> Option<Process> {
+        Some(Process::new(service_name.to_owned(), None))
+    }
+
+    /// A trace in which a `cart` server span makes a single client call.
+    fn client_call(client: SpanBuilder) -> Trace {
+        let server = SpanBuilder::new("POST /cart").kind("server").build();
+        let client = client.kind("client").child_of(&server).build();
+        Trace::from_process_spans(vec![(server, process("cart")), (client, process("cart"))])
+            .unwrap()
+    }
+
+    #[test]
+    fn peers_are_named_by_peer_service_first() {
+        let trace = client_call(
+            SpanBuilder::new("GET")
+                .tag("peer.service", "stock")
+                .tag("net.peer.name", "stock.internal")
+                .tag("http.host", "localhost"),
+        );
+        assert_eq!(trace.dependencies().callees("cart"), vec!["stock"]);
+    }
+
+    #[test]
+    fn peers_fall_back_to_their_host_and_port() {
+        let trace = client_call(
+            SpanBuilder::new("GET")
+                .tag("net.peer.name", "stock.internal")
+                .tag("net.peer.port", 8080i64)
+                .tag("http.host", "localhost"),
+        );
+        assert_eq!(
+            trace.dependencies().callees("cart"),
+            vec!["stock.internal:8080"]
+        );
+
+        let trace = client_call(SpanBuilder::new("GET").tag("http.host", "localhost"));
+        assert_eq!(trace.dependencies().callees("cart"), vec!["localhost"]);
+
+        let trace = client_call(SpanBuilder::new("GET"));
+        assert!(trace.dependencies().is_empty());
+    }
+
+    #[test]
+    fn calls_to_services_reporting_their_own_spans_are_linked_once() {
+        let server = SpanBuilder::new("POST /cart").kind("server").build();
+        let client = SpanBuilder::new("GET")
+            .kind("client")
+            .tag("peer.service", "stock")
+            .child_of(&server)
+            .build();
+        let callee = SpanBuilder::new("GET /stock")
+            .kind("server")
+            .child_of(&client)
+            .build();
+        let trace = Trace::from_process_spans(vec![
+            (server, process("cart")),
+            (client, process("cart")),
+            (callee, process("stock_service")),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            trace.dependencies().links().collect::<Vec<_>>(),
+            vec![&DependencyLink {
+                parent: "cart".to_owned(),
+                child: "stock_service".to_owned(),
+                call_count: 1,
+                error_count: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn calls_and_errors_are_counted_across_traces() {
+        let call = |failed: bool| {
+            let client = SpanBuilder::new("GET").tag("peer.service", "stock");
+            client_call(if failed {
+                client.error("timed out")
+            } else {
+                client
+            })
+        };
+        let traces = vec![call(false), call(true), call(true)];
+
+        let graph = DependencyGraph::from_traces(&traces);
+        assert_eq!(graph.callers("stock"), vec!["cart"]);
+        assert_eq!(
+            graph.link("cart", "stock"),
+            Some(&DependencyLink {
+                parent: "cart".to_owned(),
+                child: "stock".to_owned(),
+                call_count: 3,
+                error_count: 2,
+            })
+        );
+        assert_eq!(graph.to_string(), "cart -> stock (3 calls, 2 errors)\n");
+    }
+
+    #[test]
+    fn spans_from_unknown_processes_are_ignored() {
+        let server = SpanBuilder::new("POST /cart").kind("server").build();
+        let client = SpanBuilder::new("GET")
+            .kind("client")
+            .tag("peer.service", "stock")
+            .child_of(&server)
+            .build();
+        let trace =
+            Trace::from_process_spans(vec![(server, process("cart")), (client, None)]).unwrap();
+        assert!(trace.dependencies().is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crate::jaeger_models::{OwnedTagValue, Trace, TraceSpan};
+
+/// Settings controlling which differences [`Trace::diff_with`] reports.
+#[derive(Clone, Debug, Default)]
+pub struct TraceDiffSettings {
+    /// Tags which are expected to differ between runs, such as peer ports or
+    /// thread ids, and so are not compared.
+    pub ignored_tags: Vec<String>,
+}
+
+/// A single difference between two traces.
+///
+/// Spans are identified by their path from the root: the operation names of each
+/// ancestor, then of the span itself, with `#n` appended where a span is the n-th
+/// (counting from zero) of several siblings sharing an operation name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceChange {
+    /// A span, and everything below it, only exists in the new trace.
+    SpanAdded { path: String, descendants: usize },
+    /// A span, and everything below it, only exists in the old trace.
+    SpanRemoved { path: String, descendants: usize },
+    /// A tag was added, removed or given a different value.
+    TagChanged {
+        path: String,
+        key: String,
+        before: Option<OwnedTagValue>,
+        after: Option<OwnedTagValue>,
+    },
+    /// The span's children exist in both traces, but started in a different order.
+    ChildrenReordered {
+        path: String,
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+}
+
+/// The differences between two traces, found by [`Trace::diff`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceDiff {
+    pub changes: Vec<TraceChange>,
+}
+
+impl TraceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Trace {
+    /// Compare the structure of this trace against `other`, comparing every tag.
+    pub fn diff(&self, other: &Trace) -> TraceDiff {
+        self.diff_with(other, &TraceDiffSettings::default())
+    }
+
+    /// Compare the structure of this trace against `other`.
+    ///
+    /// Ids and timings differ between any two runs, so spans are instead aligned by
+    /// operation name and position in the tree: the n-th child of a given name is
+    /// matched with the n-th child of the same name under the matching parent.
+    /// Spans with no counterpart are reported as added or removed, and matched
+    /// spans are compared tag by tag.
+    pub fn diff_with(&self, other: &Trace, settings: &TraceDiffSettings) -> TraceDiff {
+        let mut changes = Vec::new();
+        let (before, after) = (self.root(), other.root());
+        if before.operation_name == after.operation_name {
+            diff_spans(
+                before,
+                after,
+                &before.operation_name,
+                settings,
+                &mut changes,
+            );
+        } else {
+            changes.push(TraceChange::SpanRemoved {
+                path: before.operation_name.clone(),
+                descendants: before.descendants().count(),
+            });
+            changes.push(TraceChange::SpanAdded {
+                path: after.operation_name.clone(),
+                descendants: after.descendants().count(),
+            });
+        }
+        TraceDiff { changes }
+    }
+}
+
+fn diff_spans(
+    before: TraceSpan,
+    after: TraceSpan,
+    path: &str,
+    settings: &TraceDiffSettings,
+    changes: &mut Vec<TraceChange>,
+) {
+    let before_tags = tags(before, settings);
+    let after_tags = tags(after, settings);
+    let keys: BTreeSet<&String> = before_tags.keys().chain(after_tags.keys()).collect();
+    for key in keys {
+        let (old, new) = (before_tags.get(key), after_tags.get(key));
+        if old != new {
+            changes.push(TraceChange::TagChanged {
+                path: path.to_owned(),
+                key: key.clone(),
+                before: old.cloned(),
+                after: new.cloned(),
+            });
+        }
+    }
+
+    let before_children = labelled_children(before);
+    let after_children = labelled_children(after);
+    let after_positions: HashMap<&str, usize> = after_children
+        .iter()
+        .enumerate()
+        .map(|(position, (label, _))| (label.as_str(), position))
+        .collect();
+
+    let mut matched_before = Vec::new();
+    for (label, child) in &before_children {
+        let child_path = format!("{} > {}", path, label);
+        match after_positions.get(label.as_str()) {
+            Some(&position) => {
+                matched_before.push(label.clone());
+                diff_spans(
+                    *child,
+                    after_children[position].1,
+                    &child_path,
+                    settings,
+                    changes,
+                );
+            }
+            None => changes.push(TraceChange::SpanRemoved {
+                path: child_path,
+                descendants: child.descendants().count(),
+            }),
+        }
+    }
+
+    let before_labels: BTreeSet<&str> = before_children.iter().map(|(l, _)| l.as_str()).collect();
+    let mut matched_after = Vec::new();
+    for (label, child) in &after_children {
+        if before_labels.contains(label.as_str()) {
+            matched_after.push(label.clone());
+        } else {
+            changes.push(TraceChange::SpanAdded {
+                path: format!("{} > {}", path, label),
+                descendants: child.descendants().count(),
+            });
+        }
+    }
+
+    if matched_before != matched_after {
+        changes.push(TraceChange::ChildrenReordered {
+            path: path.to_owned(),
+            before: matched_before,
+            after: matched_after,
+        });
+    }
+}
+
+/// Label each child with its operation name, numbered amongst any siblings
+/// sharing that name so that repeated operations can be told apart.
+fn labelled_children(span: TraceSpan) -> Vec<(String, TraceSpan)> {
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    span.children()
+        .map(|child| {
+            let occurrence = occurrences
+                .entry(child.span().operation_name.as_str())
+                .or_default();
+            let label = match *occurrence {
+                0 => child.operation_name.clone(),
+                n => format!("{}#{}", child.operation_name, n),
+            };
+            *occurrence += 1;
+            (label, child)
+        })
+        .collect()
+}
+
+fn tags(span: TraceSpan, settings: &TraceDiffSettings) -> BTreeMap<String, OwnedTagValue> {
+    span.tags
+        .iter()
+        .flatten()
+        .filter(|tag| !settings.ignored_tags.contains(&tag.key))
+        .filter_map(|tag| Some((tag.key.clone(), tag.owned_value().ok()?)))
+        .collect()
+}
+
+impl Display for TraceChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceChange::SpanAdded { path, descendants } => {
+                write!(f, "+ {}", path)?;
+                if *descendants > 0 {
+                    write!(f, " (and {} descendants)", descendants)?;
+                }
+                Ok(())
+            }
+            TraceChange::SpanRemoved { path, descendants } => {
+                write!(f, "- {}", path)?;
+                if *descendants > 0 {
+                    write!(f, " (and {} descendants)", descendants)?;
+                }
+                Ok(())
+            }
+            TraceChange::TagChanged {
+                path,
+                key,
+                before,
+                after,
+            } => match (before, after) {
+                (Some(before), Some(after)) => {
+                    write!(
+                        f,
+                        "~ {}: {} changed from {} to {}",
+                        path, key, before, after
+                    )
+                }
+                (None, Some(after)) => write!(f, "~ {}: {} added with value {}", path, key, after),
+                (Some(before), None) => write!(f, "~ {}: {} removed (was {})", path, key, before),
+                (None, None) => Ok(()),
+            },
+            TraceChange::ChildrenReordered {
+                path,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ {}: children reordered from [{}] to [{}]",
+                path,
+                before.join(", "),
+                after.join(", ")
+            ),
+        }
+    }
+}
+
+impl Display for TraceDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    /// A trace whose root has a child for each `(operation, status code)`, started
+    /// in order.
+    fn trace(children: &[(&str, i64)]) -> Trace {
+        let root = SpanBuilder::new("POST /items").build();
+        let mut spans = vec![root.clone()];
+        for (position, (operation, status_code)) in children.iter().enumerate() {
+            spans.push(
+                SpanBuilder::new(*operation)
+                    .child_of(&root)
+                    .start_offset(Duration::from_micros(position as u64 * 10))
+                    .tag("http.status_code", *status_code)
+                    .tag("net.peer.port", 40_000 + position as i64)
+                    .build(),
+            );
+        }
+        Trace::from_spans(spans).unwrap()
+    }
+
+    fn settings() -> TraceDiffSettings {
+        TraceDiffSettings {
+            ignored_tags: vec!["net.peer.port".to_owned()],
+        }
+    }
+
+    #[test]
+    fn traces_with_the_same_structure_do_not_differ() {
+        let before = trace(&[("GET /stock", 200), ("GET /price", 200)]);
+        let after = trace(&[("GET /stock", 200), ("GET /price", 200)]);
+
+        assert!(before.diff_with(&after, &settings()).is_empty());
+    }
+
+    #[test]
+    fn ignored_tags_are_not_compared() {
+        let before = trace(&[("GET /price", 200), ("GET /stock", 200)]);
+        let after = trace(&[("GET /stock", 200)]);
+
+        let diff = before.diff(&after);
+
+        assert!(diff.changes.contains(&TraceChange::TagChanged {
+            path: "POST /items > GET /stock".to_owned(),
+            key: "net.peer.port".to_owned(),
+            before: Some(OwnedTagValue::Long(40_001)),
+            after: Some(OwnedTagValue::Long(40_000)),
+        }));
+        assert!(!before
+            .diff_with(&after, &settings())
+            .changes
+            .iter()
+            .any(|change| matches!(change, TraceChange::TagChanged { .. })));
+    }
+
+    #[test]
+    fn repeated_operations_are_aligned_by_occurrence() {
+        let before = trace(&[("GET /stock", 200), ("GET /stock", 200)]);
+        let after = trace(&[
+            ("GET /stock", 200),
+            ("GET /stock", 503),
+            ("GET /stock", 200),
+        ]);
+
+        let diff = before.diff_with(&after, &settings());
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                TraceChange::TagChanged {
+                    path: "POST /items > GET /stock#1".to_owned(),
+                    key: "http.status_code".to_owned(),
+                    before: Some(OwnedTagValue::Long(200)),
+                    after: Some(OwnedTagValue::Long(503)),
+                },
+                TraceChange::SpanAdded {
+                    path: "POST /items > GET /stock#2".to_owned(),
+                    descendants: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn children_started_in_a_different_order_are_reported() {
+        let before = trace(&[("GET /stock", 200), ("GET /price", 200)]);
+        let after = trace(&[("GET /price", 200), ("GET /stock", 200)]);
+
+        let diff = before.diff_with(&after, &settings());
+
+        assert_eq!(
+            diff.changes,
+            vec![TraceChange::ChildrenReordered {
+                path: "POST /items".to_owned(),
+                before: vec!["GET /stock".to_owned(), "GET /price".to_owned()],
+                after: vec!["GET /price".to_owned(), "GET /stock".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn removed_spans_count_their_descendants() {
+        let before = trace(&[("GET /stock", 200)]);
+        let after = Trace::from_spans(vec![SpanBuilder::new("POST /items").build()]).unwrap();
+
+        let diff = before.diff_with(&after, &settings());
+
+        assert_eq!(diff.to_string(), "- POST /items > GET /stock\n");
+        assert_eq!(
+            after.diff(&before).to_string(),
+            "+ POST /items > GET /stock\n"
+        );
+    }
+}
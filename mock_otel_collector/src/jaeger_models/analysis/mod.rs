@@ -0,0 +1,11 @@
+mod critical_path;
+mod dependencies;
+mod diff;
+mod stats;
+mod timing;
+
+pub use critical_path::*;
+pub use dependencies::*;
+pub use diff::*;
+pub use stats::*;
+pub use timing::*;
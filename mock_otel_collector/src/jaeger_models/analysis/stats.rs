@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::jaeger_models::render::format_duration;
+use crate::jaeger_models::{Trace, TraceSpan};
+
+/// Aggregate facts about a single trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStats {
+    pub span_count: usize,
+    /// The number of levels in the span tree; one for a trace with only a root.
+    pub depth: usize,
+    /// The greatest number of direct children of any one span.
+    pub max_fan_out: usize,
+    /// The duration of the root span.
+    pub duration: Duration,
+    pub error_count: usize,
+    pub operations: BTreeMap<String, OperationStats>,
+}
+
+/// Facts about every span in a trace sharing an operation name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OperationStats {
+    pub count: usize,
+    pub error_count: usize,
+    /// The duration of each span, in the order the spans appear in the trace.
+    pub durations: Vec<Duration>,
+    pub total_duration: Duration,
+    /// The time spent in these spans but not within any of their children.
+    pub self_duration: Duration,
+}
+
+impl Trace {
+    /// Compute [`TraceStats`] for this trace.
+    pub fn stats(&self) -> TraceStats {
+        let mut operations: BTreeMap<String, OperationStats> = BTreeMap::new();
+        let mut error_count = 0;
+        for span in self.descendants() {
+            let is_error = span.status().is_error();
+            if is_error {
+                error_count += 1;
+            }
+
+            let duration = micros(span.duration);
+            let operation = operations.entry(span.operation_name.clone()).or_default();
+            operation.count += 1;
+            if is_error {
+                operation.error_count += 1;
+            }
+            operation.durations.push(duration);
+            operation.total_duration += duration;
+            operation.self_duration += self_duration(span);
+        }
+
+        TraceStats {
+            span_count: self.len(),
+            depth: self.descendants().map(|s| s.depth() + 1).max().unwrap_or(0),
+            max_fan_out: self
+                .descendants()
+                .map(|s| s.children().count())
+                .max()
+                .unwrap_or(0),
+            duration: micros(self.root().duration),
+            error_count,
+            operations,
+        }
+    }
+}
+
+/// The duration of `span` not covered by any of its children, clipped to the
+/// span's own bounds.
+fn self_duration(span: TraceSpan) -> Duration {
+    let start = span.start_time;
+    let end = start + span.duration.max(0);
+    let mut intervals: Vec<(i64, i64)> = span
+        .children()
+        .map(|child| {
+            (
+                child.start_time.max(start),
+                (child.start_time + child.duration.max(0)).min(end),
+            )
+        })
+        .filter(|(child_start, child_end)| child_start < child_end)
+        .collect();
+    intervals.sort_unstable();
+
+    let mut covered = 0;
+    let mut cursor = start;
+    for (child_start, child_end) in intervals {
+        let child_start = child_start.max(cursor);
+        if child_end > child_start {
+            covered += child_end - child_start;
+            cursor = child_end;
+        }
+    }
+    micros(end - start - covered)
+}
+
+/// A summary of a set of durations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DurationSummary {
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl DurationSummary {
+    pub fn from_durations(durations: &[Duration]) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = durations.to_vec();
+        sorted.sort_unstable();
+        let total: Duration = sorted.iter().sum();
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: total / sorted.len() as u32,
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        }
+    }
+}
+
+/// The nearest-rank percentile of an already sorted, non-empty slice.
+fn percentile(sorted: &[Duration], percentile: f64) -> Duration {
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Statistics aggregated over many traces, e.g. every trace produced by a load test.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AggregateTraceStats {
+    pub trace_count: usize,
+    pub span_count: usize,
+    pub error_count: usize,
+    /// The durations of the traces' root spans.
+    pub durations: DurationSummary,
+    pub operations: BTreeMap<String, OperationSummary>,
+}
+
+/// Statistics for an operation, aggregated over many traces.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OperationSummary {
+    pub count: usize,
+    pub error_count: usize,
+    pub durations: DurationSummary,
+    pub self_duration: Duration,
+}
+
+impl AggregateTraceStats {
+    pub fn from_traces<'t>(traces: impl IntoIterator<Item = &'t Trace>) -> Self {
+        Self::from_stats(traces.into_iter().map(Trace::stats))
+    }
+
+    pub fn from_stats(stats: impl IntoIterator<Item = TraceStats>) -> Self {
+        let mut aggregate = AggregateTraceStats::default();
+        let mut trace_durations = Vec::new();
+        let mut operation_durations: BTreeMap<String, Vec<Duration>> = BTreeMap::new();
+
+        for trace in stats {
+            aggregate.trace_count += 1;
+            aggregate.span_count += trace.span_count;
+            aggregate.error_count += trace.error_count;
+            trace_durations.push(trace.duration);
+
+            for (name, operation) in trace.operations {
+                let summary = aggregate.operations.entry(name.clone()).or_default();
+                summary.count += operation.count;
+                summary.error_count += operation.error_count;
+                summary.self_duration += operation.self_duration;
+                operation_durations
+                    .entry(name)
+                    .or_default()
+                    .extend(operation.durations);
+            }
+        }
+
+        aggregate.durations = DurationSummary::from_durations(&trace_durations);
+        for (name, durations) in operation_durations {
+            if let Some(summary) = aggregate.operations.get_mut(&name) {
+                summary.durations = DurationSummary::from_durations(&durations);
+            }
+        }
+        aggregate
+    }
+}
+
+/// Renders one line per operation, showing its count, errors and duration percentiles.
+impl Display for AggregateTraceStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} traces, {} spans, {} errors; trace duration p50 {} p99 {}",
+            self.trace_count,
+            self.span_count,
+            self.error_count,
+            format_duration(self.durations.p50.as_micros() as i64),
+            format_duration(self.durations.p99.as_micros() as i64),
+        )?;
+        for (name, operation) in &self.operations {
+            writeln!(
+                f,
+                "{}: count={} errors={} p50={} p90={} p99={} max={}",
+                name,
+                operation.count,
+                operation.error_count,
+                format_duration(operation.durations.p50.as_micros() as i64),
+                format_duration(operation.durations.p90.as_micros() as i64),
+                format_duration(operation.durations.p99.as_micros() as i64),
+                format_duration(operation.durations.max.as_micros() as i64),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn micros(value: i64) -> Duration {
+    Duration::from_micros(value.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    fn millis(durations: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        durations.into_iter().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let summary = DurationSummary::from_durations(&millis((1..=10).rev()));
+
+        assert_eq!(summary.count, 10);
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.max, Duration::from_millis(10));
+        assert_eq!(summary.mean, Duration::from_micros(5_500));
+        assert_eq!(summary.p50, Duration::from_millis(5));
+        assert_eq!(summary.p90, Duration::from_millis(9));
+        assert_eq!(summary.p95, Duration::from_millis(10));
+        assert_eq!(summary.p99, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn percentiles_of_a_single_duration_are_that_duration() {
+        let summary = DurationSummary::from_durations(&millis([7]));
+
+        assert_eq!(summary.p50, Duration::from_millis(7));
+        assert_eq!(summary.p99, Duration::from_millis(7));
+    }
+
+    #[test]
+    fn empty_summaries_are_zero() {
+        assert_eq!(
+            DurationSummary::from_durations(&[]),
+            DurationSummary::default()
+        );
+    }
+
+    #[test]
+    fn self_durations_exclude_overlapping_children() {
+        let root = SpanBuilder::new("root")
+            .start_time(0)
+            .duration(Duration::from_micros(100))
+            .build();
+        let child = |start, duration| {
+            SpanBuilder::new("child")
+                .child_of(&root)
+                .start_time(start)
+                .duration(Duration::from_micros(duration))
+                .build()
+        };
+        let trace = Trace::from_spans(vec![
+            root.clone(),
+            child(10, 30),
+            child(20, 30),
+            child(90, 50),
+        ])
+        .unwrap();
+
+        let stats = trace.stats();
+
+        assert_eq!(stats.span_count, 4);
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.max_fan_out, 3);
+        assert_eq!(
+            stats.operations["root"].self_duration,
+            Duration::from_micros(50)
+        );
+        assert_eq!(stats.operations["child"].count, 3);
+    }
+
+    #[test]
+    fn aggregates_pool_durations_across_traces() {
+        let trace = |duration| {
+            Trace::from_spans(vec![SpanBuilder::new("root")
+                .duration(Duration::from_millis(duration))
+                .build()])
+            .unwrap()
+        };
+        let traces: Vec<Trace> = (1..=4).map(trace).collect();
+
+        let aggregate = AggregateTraceStats::from_traces(&traces);
+
+        assert_eq!(aggregate.trace_count, 4);
+        assert_eq!(aggregate.durations.p50, Duration::from_millis(2));
+        assert_eq!(
+            aggregate.operations["root"].durations.max,
+            Duration::from_millis(4)
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::jaeger_models::{SpanId, Trace, TraceSpan};
+
+/// Settings controlling how strictly [`validate_timing`] treats a trace.
+#[derive(Clone, Debug, Default)]
+pub struct TimingValidationSettings {
+    /// How far a child span may extend beyond its parent's bounds when the two
+    /// were reported by different processes, whose clocks may disagree.
+    /// Spans reported by the same process are held to their parent's bounds exactly.
+    pub skew_tolerance: Duration,
+
+    /// Whether spans with a duration of zero are acceptable. Negative durations
+    /// are always reported.
+    pub allow_zero_durations: bool,
+}
+
+/// A problem with the timing of a single span.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimingIssue {
+    /// The span's duration was zero or negative, in microseconds.
+    NonPositiveDuration {
+        span: SpanDescription,
+        duration: i64,
+    },
+    /// The span started before its parent did.
+    StartsBeforeParent {
+        span: SpanDescription,
+        parent: SpanDescription,
+        by: Duration,
+    },
+    /// The span finished after its parent did.
+    EndsAfterParent {
+        span: SpanDescription,
+        parent: SpanDescription,
+        by: Duration,
+    },
+    /// The span has no start time, or recorded events after it finished,
+    /// suggesting it was closed early or never properly closed.
+    LooksUnclosed {
+        span: SpanDescription,
+        reason: String,
+    },
+}
+
+/// Enough information to identify a span in a report.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanDescription {
+    pub span_id: SpanId,
+    pub operation_name: String,
+}
+
+impl From<TraceSpan<'_>> for SpanDescription {
+    fn from(span: TraceSpan) -> Self {
+        Self {
+            span_id: span.id(),
+            operation_name: span.operation_name.clone(),
+        }
+    }
+}
+
+/// The error returned by [`validate_timing`] when at least one issue was found.
+#[derive(Clone, Debug)]
+pub struct TimingValidationError {
+    pub issues: Vec<TimingIssue>,
+}
+
+/// Check that the timings recorded in a trace are consistent: every span has a
+/// positive duration, falls within its parent's bounds, and looks to have been
+/// closed properly.
+pub fn validate_timing(
+    trace: &Trace,
+    settings: &TimingValidationSettings,
+) -> Result<(), TimingValidationError> {
+    let mut issues = Vec::new();
+
+    for span in trace.descendants() {
+        if span.duration < 0 || (span.duration == 0 && !settings.allow_zero_durations) {
+            issues.push(TimingIssue::NonPositiveDuration {
+                span: span.into(),
+                duration: span.duration,
+            });
+        }
+
+        if span.start_time <= 0 {
+            issues.push(TimingIssue::LooksUnclosed {
+                span: span.into(),
+                reason: "it has no start time".to_owned(),
+            });
+        }
+
+        let span_end = span.start_time + span.duration;
+        if let Some(latest_log) = span.logs.iter().flatten().map(|l| l.timestamp).max() {
+            if latest_log > span_end {
+                issues.push(TimingIssue::LooksUnclosed {
+                    span: span.into(),
+                    reason: format!(
+                        "it recorded an event {:?} after it finished",
+                        Duration::from_micros((latest_log - span_end) as u64)
+                    ),
+                });
+            }
+        }
+
+        if let Some(parent) = span.parent() {
+            let tolerance = if span.process() == parent.process() {
+                0
+            } else {
+                settings.skew_tolerance.as_micros() as i64
+            };
+
+            let started_early_by = parent.start_time - span.start_time;
+            if started_early_by > tolerance {
+                issues.push(TimingIssue::StartsBeforeParent {
+                    span: span.into(),
+                    parent: parent.into(),
+                    by: Duration::from_micros(started_early_by as u64),
+                });
+            }
+
+            let parent_end = parent.start_time + parent.duration;
+            let finished_late_by = span_end - parent_end;
+            if finished_late_by > tolerance {
+                issues.push(TimingIssue::EndsAfterParent {
+                    span: span.into(),
+                    parent: parent.into(),
+                    by: Duration::from_micros(finished_late_by as u64),
+                });
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(TimingValidationError { issues })
+    }
+}
+
+impl Display for SpanDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, r#""{}" ({})"#, self.operation_name, self.span_id)
+    }
+}
+
+impl Display for TimingIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingIssue::NonPositiveDuration { span, duration } => {
+                write!(f, "Span {} has a duration of {}µs", span, duration)
+            }
+            TimingIssue::StartsBeforeParent { span, parent, by } => write!(
+                f,
+                "Span {} starts {:?} before its parent {}",
+                span, by, parent
+            ),
+            TimingIssue::EndsAfterParent { span, parent, by } => {
+                write!(f, "Span {} ends {:?} after its parent {}", span, by, parent)
+            }
+            TimingIssue::LooksUnclosed { span, reason } => {
+                write!(f, "Span {} looks unclosed: {}", span, reason)
+            }
+        }
+    }
+}
+
+impl Display for TimingValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Trace timings are inconsistent:")?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TimingValidationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{Process, Span, SpanBuilder};
+
+    const START: i64 = 1_000_000;
+
+    fn root() -> Span {
+        SpanBuilder::new("root")
+            .start_time(START)
+            .duration(Duration::from_micros(100))
+            .build()
+    }
+
+    fn child(root: &Span, start: i64, duration: u64) -> Span {
+        SpanBuilder::new("child")
+            .child_of(root)
+            .start_time(START + start)
+            .duration(Duration::from_micros(duration))
+            .build()
+    }
+
+    /// Assemble the root and child, reported by the given services.
+    fn trace(root: Span, child: Span, services: (&str, &str)) -> Trace {
+        let process = |service: &str| Some(Process::new(service.to_owned(), None));
+        Trace::from_process_spans(vec![
+            (root, process(services.0)),
+            (child, process(services.1)),
+        ])
+        .unwrap()
+    }
+
+    fn issues(trace: &Trace, settings: &TimingValidationSettings) -> Vec<String> {
+        validate_timing(trace, settings)
+            .err()
+            .map(|error| error.issues.iter().map(ToString::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn children_within_their_parent_are_valid() {
+        let root = root();
+        let trace = trace(root.clone(), child(&root, 10, 80), ("svc", "svc"));
+
+        assert!(validate_timing(&trace, &TimingValidationSettings::default()).is_ok());
+    }
+
+    #[test]
+    fn children_starting_before_their_parent_are_reported() {
+        let root = root();
+        let child = child(&root, -5, 50);
+        let trace = trace(root, child, ("svc", "svc"));
+
+        let error = validate_timing(&trace, &TimingValidationSettings::default()).unwrap_err();
+
+        assert!(matches!(
+            &error.issues[..],
+            [TimingIssue::StartsBeforeParent { by, .. }] if *by == Duration::from_micros(5)
+        ));
+    }
+
+    #[test]
+    fn children_ending_after_their_parent_are_reported() {
+        let root = root();
+        let child = child(&root, 60, 50);
+        let trace = trace(root, child, ("svc", "svc"));
+
+        let error = validate_timing(&trace, &TimingValidationSettings::default()).unwrap_err();
+
+        assert!(matches!(
+            &error.issues[..],
+            [TimingIssue::EndsAfterParent { by, .. }] if *by == Duration::from_micros(10)
+        ));
+    }
+
+    #[test]
+    fn negative_durations_are_always_reported() {
+        let root = root();
+        let mut child = child(&root, 10, 0);
+        child.duration = -5;
+        let trace = trace(root, child, ("svc", "svc"));
+        let settings = TimingValidationSettings {
+            allow_zero_durations: true,
+            ..Default::default()
+        };
+
+        let error = validate_timing(&trace, &settings).unwrap_err();
+
+        assert!(matches!(
+            &error.issues[..],
+            [TimingIssue::NonPositiveDuration { duration: -5, .. }]
+        ));
+    }
+
+    #[test]
+    fn zero_durations_are_only_reported_unless_allowed() {
+        let root = root();
+        let child = child(&root, 10, 0);
+        let trace = trace(root, child, ("svc", "svc"));
+        let allowing = TimingValidationSettings {
+            allow_zero_durations: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            issues(&trace, &TimingValidationSettings::default()).len(),
+            1
+        );
+        assert!(issues(&trace, &allowing).is_empty());
+    }
+
+    #[test]
+    fn skew_is_only_tolerated_between_different_processes() {
+        let root = root();
+        let skewed = child(&root, -5, 110);
+        let settings = TimingValidationSettings {
+            skew_tolerance: Duration::from_micros(10),
+            ..Default::default()
+        };
+
+        let same_process = trace(root.clone(), skewed.clone(), ("svc", "svc"));
+        let other_process = trace(root, skewed, ("svc", "other"));
+
+        assert_eq!(
+            issues(&same_process, &settings),
+            [
+                format!(
+                    r#"Span "child" ({}) starts 5µs before its parent "root" ({})"#,
+                    same_process.find_by_operation("child").unwrap().id(),
+                    same_process.root().id()
+                ),
+                format!(
+                    r#"Span "child" ({}) ends 5µs after its parent "root" ({})"#,
+                    same_process.find_by_operation("child").unwrap().id(),
+                    same_process.root().id()
+                ),
+            ]
+        );
+        assert!(issues(&other_process, &settings).is_empty());
+    }
+}
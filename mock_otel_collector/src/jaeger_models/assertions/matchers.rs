@@ -0,0 +1,324 @@
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Bound, RangeBounds};
+
+use regex::Regex;
+
+use crate::jaeger_models::{OwnedTagValue, Span, TagValue};
+
+/// Matches strings, such as operation names or string tag values, which may
+/// contain dynamic parts like ids.
+///
+/// Plain strings convert into an exact matcher, so anything accepting
+/// `impl Into<StringMatcher>` can still be given the exact name to look for.
+#[derive(Clone, Debug)]
+pub enum StringMatcher {
+    Exact(String),
+    Prefix(String),
+    /// A pattern where `*` matches any run of characters and `?` any single one.
+    Glob(String, Regex),
+    Regex(Regex),
+    AnyOf(Vec<StringMatcher>),
+}
+
+impl StringMatcher {
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        StringMatcher::Prefix(prefix.into())
+    }
+
+    /// Match the whole string against a glob, e.g. `GET /stock/*`.
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        let mut regex = String::from("^");
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        let regex = Regex::new(&regex).expect("Escaped glob patterns are always valid regexes");
+        StringMatcher::Glob(pattern, regex)
+    }
+
+    /// Match against a regular expression, which is found anywhere within the
+    /// string unless anchored with `^` and `$`.
+    pub fn regex(pattern: &str) -> Result<Self, anyhow::Error> {
+        Ok(StringMatcher::Regex(Regex::new(pattern)?))
+    }
+
+    pub fn any_of(matchers: impl IntoIterator<Item = impl Into<StringMatcher>>) -> Self {
+        StringMatcher::AnyOf(matchers.into_iter().map(Into::into).collect())
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            StringMatcher::Exact(expected) => value == expected,
+            StringMatcher::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            StringMatcher::Glob(_, regex) | StringMatcher::Regex(regex) => regex.is_match(value),
+            StringMatcher::AnyOf(matchers) => matchers.iter().any(|m| m.matches(value)),
+        }
+    }
+}
+
+impl From<&str> for StringMatcher {
+    fn from(value: &str) -> Self {
+        StringMatcher::Exact(value.to_owned())
+    }
+}
+
+impl From<&String> for StringMatcher {
+    fn from(value: &String) -> Self {
+        StringMatcher::Exact(value.clone())
+    }
+}
+
+impl From<String> for StringMatcher {
+    fn from(value: String) -> Self {
+        StringMatcher::Exact(value)
+    }
+}
+
+impl From<Regex> for StringMatcher {
+    fn from(regex: Regex) -> Self {
+        StringMatcher::Regex(regex)
+    }
+}
+
+impl Display for StringMatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StringMatcher::Exact(expected) => write!(f, "{}", expected),
+            StringMatcher::Prefix(prefix) => write!(f, "{}*", prefix),
+            StringMatcher::Glob(pattern, _) => write!(f, "{}", pattern),
+            StringMatcher::Regex(regex) => write!(f, "/{}/", regex),
+            StringMatcher::AnyOf(matchers) => write_any_of(f, matchers),
+        }
+    }
+}
+
+/// Matches tag values.
+///
+/// Values of any type convert into an equality matcher, and [`StringMatcher`]s
+/// into a matcher for string values, so anything accepting
+/// `impl Into<ValueMatcher>` can be given either.
+#[derive(Clone, Debug)]
+pub enum ValueMatcher {
+    /// Equal to the given value. Numeric values also match tags which record
+    /// the number as a string, as some instrumentation does.
+    Equals(OwnedTagValue),
+    String(StringMatcher),
+    /// A number, or a string holding one, within the given bounds.
+    Range(Bound<f64>, Bound<f64>),
+    AnyOf(Vec<ValueMatcher>),
+}
+
+impl ValueMatcher {
+    /// Match numbers within a range, e.g. `ValueMatcher::range(500..=599)`.
+    pub fn range<T: Copy + Into<f64>>(range: impl RangeBounds<T>) -> Self {
+        let convert = |bound: Bound<&T>| match bound {
+            Bound::Included(value) => Bound::Included((*value).into()),
+            Bound::Excluded(value) => Bound::Excluded((*value).into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        ValueMatcher::Range(convert(range.start_bound()), convert(range.end_bound()))
+    }
+
+    pub fn any_of(matchers: impl IntoIterator<Item = impl Into<ValueMatcher>>) -> Self {
+        ValueMatcher::AnyOf(matchers.into_iter().map(Into::into).collect())
+    }
+
+    pub fn matches(&self, value: &TagValue) -> bool {
+        match self {
+            ValueMatcher::Equals(expected) => match (expected, value) {
+                (OwnedTagValue::Long(expected), TagValue::String(actual)) => {
+                    actual.parse::<i64>().ok() == Some(*expected)
+                }
+                (OwnedTagValue::Double(expected), TagValue::String(actual)) => {
+                    actual.parse::<f64>().ok() == Some(*expected)
+                }
+                _ => expected == value,
+            },
+            ValueMatcher::String(matcher) => match value {
+                TagValue::String(actual) => matcher.matches(actual),
+                _ => false,
+            },
+            ValueMatcher::Range(start, end) => {
+                let number = match value {
+                    TagValue::Long(value) => Some(*value as f64),
+                    TagValue::Double(value) => Some(*value),
+                    TagValue::String(value) => value.parse::<f64>().ok(),
+                    _ => None,
+                };
+                number.map_or(false, |number| (*start, *end).contains(&number))
+            }
+            ValueMatcher::AnyOf(matchers) => matchers.iter().any(|m| m.matches(value)),
+        }
+    }
+}
+
+impl From<StringMatcher> for ValueMatcher {
+    fn from(matcher: StringMatcher) -> Self {
+        ValueMatcher::String(matcher)
+    }
+}
+
+impl From<OwnedTagValue> for ValueMatcher {
+    fn from(value: OwnedTagValue) -> Self {
+        ValueMatcher::Equals(value)
+    }
+}
+
+impl From<&str> for ValueMatcher {
+    fn from(value: &str) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<String> for ValueMatcher {
+    fn from(value: String) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<bool> for ValueMatcher {
+    fn from(value: bool) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<i64> for ValueMatcher {
+    fn from(value: i64) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<i32> for ValueMatcher {
+    fn from(value: i32) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<u32> for ValueMatcher {
+    fn from(value: u32) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<u16> for ValueMatcher {
+    fn from(value: u16) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl From<f64> for ValueMatcher {
+    fn from(value: f64) -> Self {
+        ValueMatcher::Equals(value.into())
+    }
+}
+
+impl Display for ValueMatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueMatcher::Equals(value) => write!(f, "= {}", value),
+            ValueMatcher::String(matcher) => write!(f, "matching {}", matcher),
+            ValueMatcher::Range(start, end) => {
+                let lower = match start {
+                    Bound::Included(value) => Some(format!(">= {}", value)),
+                    Bound::Excluded(value) => Some(format!("> {}", value)),
+                    Bound::Unbounded => None,
+                };
+                let upper = match end {
+                    Bound::Included(value) => Some(format!("<= {}", value)),
+                    Bound::Excluded(value) => Some(format!("< {}", value)),
+                    Bound::Unbounded => None,
+                };
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => write!(f, "{} and {}", lower, upper),
+                    (Some(bound), None) | (None, Some(bound)) => write!(f, "{}", bound),
+                    (None, None) => write!(f, "any number"),
+                }
+            }
+            ValueMatcher::AnyOf(matchers) => write_any_of(f, matchers),
+        }
+    }
+}
+
+fn write_any_of(f: &mut Formatter<'_>, matchers: &[impl Display]) -> fmt::Result {
+    write!(f, "any of [")?;
+    for (position, matcher) in matchers.iter().enumerate() {
+        if position > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", matcher)?;
+    }
+    write!(f, "]")
+}
+
+impl Span {
+    /// Whether the span has a tag with the given key whose value satisfies `matcher`.
+    pub fn tag_matches(&self, key: &str, matcher: impl Into<ValueMatcher>) -> bool {
+        let matcher = matcher.into();
+        self.get_tag(key)
+            .and_then(|tag| tag.value().ok())
+            .map_or(false, |value| matcher.matches(&value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_the_whole_string() {
+        let matcher = StringMatcher::glob("GET /stock/*");
+
+        assert!(matcher.matches("GET /stock/1234"));
+        assert!(matcher.matches("GET /stock/"));
+        assert!(!matcher.matches("GET /stock"));
+        assert!(!matcher.matches("HTTP GET /stock/1234"));
+        assert_eq!(matcher.to_string(), "GET /stock/*");
+    }
+
+    #[test]
+    fn globs_treat_other_characters_literally() {
+        let matcher = StringMatcher::glob("GET /items?id=?");
+
+        assert!(matcher.matches("GET /items?id=7"));
+        assert!(!matcher.matches("GET /items?id=17"));
+        assert!(StringMatcher::glob("a.b").matches("a.b"));
+        assert!(!StringMatcher::glob("a.b").matches("axb"));
+    }
+
+    #[test]
+    fn regexes_match_anywhere_unless_anchored() {
+        let unanchored = StringMatcher::regex(r"/stock/\d+").unwrap();
+        let anchored = StringMatcher::regex(r"^/stock/\d+$").unwrap();
+
+        assert!(unanchored.matches("GET /stock/1234"));
+        assert!(!anchored.matches("GET /stock/1234"));
+        assert!(anchored.matches("/stock/1234"));
+        assert_eq!(anchored.to_string(), r"/^/stock/\d+$/");
+        assert!(StringMatcher::regex("(").is_err());
+    }
+
+    #[test]
+    fn string_matchers_only_match_string_values() {
+        let matcher = ValueMatcher::from(StringMatcher::glob("4*"));
+
+        assert!(matcher.matches(&TagValue::String("404")));
+        assert!(!matcher.matches(&TagValue::Long(404)));
+    }
+
+    #[test]
+    fn numeric_matchers_accept_numbers_recorded_as_strings() {
+        assert!(ValueMatcher::from(200).matches(&TagValue::String("200")));
+        assert!(ValueMatcher::range(500..=599).matches(&TagValue::String("503")));
+        assert!(ValueMatcher::range(500..=599).matches(&TagValue::Long(599)));
+        assert!(!ValueMatcher::range(500..600).matches(&TagValue::Long(600)));
+        assert_eq!(
+            ValueMatcher::range(500..600).to_string(),
+            ">= 500 and < 600"
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod matchers;
+mod shape;
+
+pub use matchers::*;
+pub use shape::*;
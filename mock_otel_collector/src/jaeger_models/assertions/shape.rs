@@ -0,0 +1,529 @@
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use crate::jaeger_models::{
+    SpanStatusCode, StringMatcher, TagValue, Trace, TraceSpan, ValueMatcher,
+};
+
+/// A check which can be made against a received trace, such as a [`TraceShape`]
+/// or a closure returning an error describing what was wrong.
+pub trait TraceAssertion {
+    fn check(&self, trace: &Trace) -> Result<(), anyhow::Error>;
+}
+
+impl<F> TraceAssertion for F
+where
+    F: Fn(&Trace) -> Result<(), anyhow::Error>,
+{
+    fn check(&self, trace: &Trace) -> Result<(), anyhow::Error> {
+        self(trace)
+    }
+}
+
+/// Whether the children of a [`SpanShape`] must start in the order they were
+/// declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChildOrder {
+    Strict,
+    Any,
+}
+
+#[derive(Clone)]
+enum TagCheck {
+    Present,
+    Matches(ValueMatcher),
+    Predicate(Arc<dyn Fn(&TagValue) -> bool + Send + Sync>, String),
+}
+
+/// The expected shape of a span and the subtree below it.
+///
+/// Child shapes describe how many of a span's children should match them, which
+/// is exactly one unless stated otherwise. By default, children may start in any
+/// order and children not described by any shape are ignored.
+#[derive(Clone)]
+pub struct SpanShape {
+    operation_name: StringMatcher,
+    tags: Vec<(String, TagCheck)>,
+    status: Option<SpanStatusCode>,
+    children: Vec<SpanShape>,
+    child_order: ChildOrder,
+    allow_other_children: bool,
+    min_count: usize,
+    max_count: Option<usize>,
+}
+
+impl SpanShape {
+    /// A span whose operation name matches `operation_name`, which may be an
+    /// exact name or a [`StringMatcher`] such as a glob.
+    pub fn new(operation_name: impl Into<StringMatcher>) -> Self {
+        Self {
+            operation_name: operation_name.into(),
+            tags: Vec::new(),
+            status: None,
+            children: Vec::new(),
+            child_order: ChildOrder::Any,
+            allow_other_children: true,
+            min_count: 1,
+            max_count: Some(1),
+        }
+    }
+
+    /// Require a tag whose value matches `value`, which may be an exact value or
+    /// a [`ValueMatcher`] such as a numeric range.
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<ValueMatcher>) -> Self {
+        self.tags
+            .push((key.into(), TagCheck::Matches(value.into())));
+        self
+    }
+
+    /// Require a tag to be present, whatever its value.
+    pub fn has_tag(mut self, key: impl Into<String>) -> Self {
+        self.tags.push((key.into(), TagCheck::Present));
+        self
+    }
+
+    /// Require a tag whose value satisfies `predicate`, described in any
+    /// mismatch report by `description`.
+    pub fn tag_matching(
+        mut self,
+        key: impl Into<String>,
+        description: impl Into<String>,
+        predicate: impl Fn(&TagValue) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.tags.push((
+            key.into(),
+            TagCheck::Predicate(Arc::new(predicate), description.into()),
+        ));
+        self
+    }
+
+    /// Require the span to have the given status, as read by [`Span::status`](crate::jaeger_models::Span::status).
+    pub fn status(mut self, status: SpanStatusCode) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn child(mut self, child: SpanShape) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Require the children to start in the order in which they were declared.
+    pub fn children_in_order(mut self) -> Self {
+        self.child_order = ChildOrder::Strict;
+        self
+    }
+
+    /// Treat any child not matching one of the declared child shapes as a mismatch.
+    pub fn no_other_children(mut self) -> Self {
+        self.allow_other_children = false;
+        self
+    }
+
+    /// Expect exactly `count` spans to match this shape.
+    pub fn times(mut self, count: usize) -> Self {
+        self.min_count = count;
+        self.max_count = Some(count);
+        self
+    }
+
+    pub fn at_least(mut self, count: usize) -> Self {
+        self.min_count = count;
+        self.max_count = None;
+        self
+    }
+
+    pub fn at_most(mut self, count: usize) -> Self {
+        self.min_count = 0;
+        self.max_count = Some(count);
+        self
+    }
+
+    /// Expect no more than one span to match this shape.
+    pub fn optional(self) -> Self {
+        self.at_most(1)
+    }
+
+    fn names(&self, span: TraceSpan) -> bool {
+        self.operation_name.matches(&span.operation_name)
+    }
+
+    fn cardinality(&self) -> String {
+        match (self.min_count, self.max_count) {
+            (min, Some(max)) if min == max => format!("exactly {}", min),
+            (0, Some(max)) => format!("at most {}", max),
+            (min, None) => format!("at least {}", min),
+            (min, Some(max)) => format!("between {} and {}", min, max),
+        }
+    }
+
+    fn accepts_count(&self, count: usize) -> bool {
+        count >= self.min_count && self.max_count.map_or(true, |max| count <= max)
+    }
+
+    fn check(&self, span: TraceSpan, path: &str, mismatches: &mut Vec<ShapeMismatch>) {
+        let mut mismatch = |message: String| {
+            mismatches.push(ShapeMismatch {
+                path: path.to_owned(),
+                message,
+            })
+        };
+
+        if !self.names(span) {
+            mismatch(format!(
+                "expected operation {}, found {}",
+                self.operation_name, span.operation_name
+            ));
+        }
+
+        for (key, check) in &self.tags {
+            let value = span.get_tag(key).and_then(|tag| tag.value().ok());
+            match (check, value) {
+                (_, None) => mismatch(format!(
+                    "expected tag {} {}, but it was missing",
+                    key, check
+                )),
+                (TagCheck::Present, Some(_)) => {}
+                (TagCheck::Matches(matcher), Some(actual)) => {
+                    if !matcher.matches(&actual) {
+                        mismatch(format!("expected tag {} {}, found {}", key, check, actual));
+                    }
+                }
+                (TagCheck::Predicate(predicate, _), Some(actual)) => {
+                    if !predicate(&actual) {
+                        mismatch(format!("expected tag {} {}, found {}", key, check, actual));
+                    }
+                }
+            }
+        }
+
+        if let Some(expected) = self.status {
+            let actual = span.status().code;
+            if actual != expected {
+                mismatch(format!(
+                    "expected status {:?}, found {:?}",
+                    expected, actual
+                ));
+            }
+        }
+
+        self.check_children(span, path, mismatches);
+    }
+
+    fn check_children(&self, span: TraceSpan, path: &str, mismatches: &mut Vec<ShapeMismatch>) {
+        let children: Vec<TraceSpan> = span.children().collect();
+        let mut claimed = vec![false; children.len()];
+        let mut previous: Option<(&SpanShape, usize)> = None;
+
+        for shape in &self.children {
+            let matching: Vec<usize> = (0..children.len())
+                .filter(|&position| shape.matches(children[position]))
+                .collect();
+            for &position in &matching {
+                claimed[position] = true;
+            }
+
+            shape.check_count(
+                &matching,
+                children.iter().copied(),
+                path,
+                "child spans",
+                mismatches,
+            );
+
+            if self.child_order == ChildOrder::Strict {
+                if let (Some(&first), Some((previous_shape, previous_last))) =
+                    (matching.first(), previous)
+                {
+                    if first < previous_last {
+                        mismatches.push(ShapeMismatch {
+                            path: path.to_owned(),
+                            message: format!(
+                                "expected {} to start after {}",
+                                shape.operation_name, previous_shape.operation_name
+                            ),
+                        });
+                    }
+                }
+                if let Some(&last) = matching.last() {
+                    previous = Some((shape, last));
+                }
+            }
+        }
+
+        if !self.allow_other_children {
+            for (child, _) in children.iter().zip(claimed).filter(|(_, claimed)| !claimed) {
+                mismatches.push(ShapeMismatch {
+                    path: path.to_owned(),
+                    message: format!("unexpected child span {}", child.operation_name),
+                });
+            }
+        }
+    }
+
+    /// Report a mismatch if the number of candidates matching this shape is not
+    /// as expected. If none matched, the mismatches of the closest candidate with
+    /// the right operation name are reported too, to explain why.
+    fn check_count<'t>(
+        &self,
+        matching: &[usize],
+        candidates: impl Iterator<Item = TraceSpan<'t>>,
+        path: &str,
+        noun: &str,
+        mismatches: &mut Vec<ShapeMismatch>,
+    ) {
+        if self.accepts_count(matching.len()) {
+            return;
+        }
+        mismatches.push(ShapeMismatch {
+            path: path.to_owned(),
+            message: format!(
+                "expected {} {} matching {}, found {}",
+                self.cardinality(),
+                noun,
+                self.operation_name,
+                matching.len()
+            ),
+        });
+
+        if matching.is_empty() {
+            let closest = candidates
+                .filter(|candidate| self.names(*candidate))
+                .map(|candidate| {
+                    let mut candidate_mismatches = Vec::new();
+                    self.check(candidate, &span_path(candidate), &mut candidate_mismatches);
+                    candidate_mismatches
+                })
+                .min_by_key(|candidate_mismatches| candidate_mismatches.len());
+            mismatches.extend(closest.into_iter().flatten());
+        }
+    }
+
+    fn matches(&self, span: TraceSpan) -> bool {
+        let mut mismatches = Vec::new();
+        self.check(span, "", &mut mismatches);
+        mismatches.is_empty()
+    }
+}
+
+/// The expected shape of a trace: the shape of its root span, and of any spans
+/// expected to appear somewhere within it.
+#[derive(Clone, Default)]
+pub struct TraceShape {
+    root: Option<SpanShape>,
+    anywhere: Vec<SpanShape>,
+}
+
+impl TraceShape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the root span, and so the whole tree, to match `shape`.
+    pub fn root(mut self, shape: SpanShape) -> Self {
+        self.root = Some(shape);
+        self
+    }
+
+    /// Require spans matching `shape` to appear anywhere in the trace, however
+    /// deeply nested. The shape's cardinality applies to the whole trace.
+    pub fn contains(mut self, shape: SpanShape) -> Self {
+        self.anywhere.push(shape);
+        self
+    }
+
+    /// Check the trace against this shape, reporting every mismatch found.
+    pub fn mismatches(&self, trace: &Trace) -> Vec<ShapeMismatch> {
+        let mut mismatches = Vec::new();
+        let root = trace.root();
+        if let Some(shape) = &self.root {
+            shape.check(root, &span_path(root), &mut mismatches);
+        }
+        for shape in &self.anywhere {
+            let matching: Vec<usize> = trace
+                .descendants()
+                .filter(|span| shape.matches(*span))
+                .map(|span| span.index())
+                .collect();
+            shape.check_count(
+                &matching,
+                trace.descendants(),
+                "trace",
+                "spans",
+                &mut mismatches,
+            );
+        }
+        mismatches
+    }
+}
+
+impl TraceAssertion for TraceShape {
+    fn check(&self, trace: &Trace) -> Result<(), anyhow::Error> {
+        let mismatches = self.mismatches(trace);
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(ShapeMismatches(mismatches).into())
+        }
+    }
+}
+
+/// A single way in which a span failed to match its expected shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeMismatch {
+    /// The operation names from the root down to the span in question.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ShapeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Every mismatch found when checking a trace against a [`TraceShape`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeMismatches(pub Vec<ShapeMismatch>);
+
+impl Display for ShapeMismatches {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trace did not match the expected shape:")?;
+        for mismatch in &self.0 {
+            writeln!(f, "  - {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShapeMismatches {}
+
+impl Display for TagCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TagCheck::Present => write!(f, "to be present"),
+            TagCheck::Matches(matcher) => write!(f, "{}", matcher),
+            TagCheck::Predicate(_, description) => write!(f, "{}", description),
+        }
+    }
+}
+
+/// The operation names from the root down to `span`, e.g. `POST /items > SELECT`.
+fn span_path(span: TraceSpan) -> String {
+    let mut names: Vec<&str> = span
+        .ancestors()
+        .map(|ancestor| ancestor.span().operation_name.as_str())
+        .collect();
+    names.reverse();
+    names.push(&span.span().operation_name);
+    names.join(" > ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    /// `POST /items`, which calls `GET /stock` twice and then fails a `SELECT`.
+    fn trace() -> Trace {
+        let root = SpanBuilder::new("POST /items").kind("server").build();
+        let child = |operation: &str, position: u64| {
+            SpanBuilder::new(operation)
+                .child_of(&root)
+                .start_offset(Duration::from_micros(position * 10))
+        };
+        Trace::from_spans(vec![
+            root.clone(),
+            child("GET /stock", 0)
+                .tag("http.status_code", 200i64)
+                .build(),
+            child("GET /stock", 1)
+                .tag("http.status_code", 200i64)
+                .build(),
+            child("SELECT", 2).error("connection reset").build(),
+        ])
+        .unwrap()
+    }
+
+    fn messages(shape: &TraceShape) -> Vec<String> {
+        shape
+            .mismatches(&trace())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn children_are_counted_exactly() {
+        let shape = |count| {
+            TraceShape::new().root(
+                SpanShape::new("POST /items").child(SpanShape::new("GET /stock").times(count)),
+            )
+        };
+
+        assert!(messages(&shape(2)).is_empty());
+        assert_eq!(
+            messages(&shape(3)),
+            ["POST /items: expected exactly 3 child spans matching GET /stock, found 2"]
+        );
+    }
+
+    #[test]
+    fn optional_children_may_be_missing_but_not_repeated() {
+        let shape = |operation| {
+            TraceShape::new()
+                .root(SpanShape::new("POST /items").child(SpanShape::new(operation).optional()))
+        };
+
+        assert!(messages(&shape("GET /price")).is_empty());
+        assert!(messages(&shape("SELECT")).is_empty());
+        assert_eq!(
+            messages(&shape("GET /stock")),
+            ["POST /items: expected at most 1 child spans matching GET /stock, found 2"]
+        );
+    }
+
+    #[test]
+    fn undeclared_children_are_only_reported_when_disallowed() {
+        let root = SpanShape::new("POST /items").child(SpanShape::new("GET /stock").at_least(1));
+
+        assert!(messages(&TraceShape::new().root(root.clone())).is_empty());
+        assert_eq!(
+            messages(&TraceShape::new().root(root.no_other_children())),
+            ["POST /items: unexpected child span SELECT"]
+        );
+    }
+
+    #[test]
+    fn strictly_ordered_children_must_start_in_order() {
+        let root = SpanShape::new("POST /items")
+            .child(SpanShape::new("SELECT"))
+            .child(SpanShape::new("GET /stock").times(2));
+
+        assert!(messages(&TraceShape::new().root(root.clone())).is_empty());
+        assert_eq!(
+            messages(&TraceShape::new().root(root.children_in_order())),
+            ["POST /items: expected GET /stock to start after SELECT"]
+        );
+    }
+
+    #[test]
+    fn missing_spans_are_explained_by_the_closest_candidate() {
+        let shape = TraceShape::new().contains(
+            SpanShape::new("SELECT")
+                .tag("db.system", "postgresql")
+                .status(SpanStatusCode::Ok),
+        );
+
+        assert_eq!(
+            messages(&shape),
+            [
+                "trace: expected exactly 1 spans matching SELECT, found 0",
+                "POST /items > SELECT: expected tag db.system = postgresql, but it was missing",
+                "POST /items > SELECT: expected status Ok, found Error",
+            ]
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Batch, Log, OwnedTagValue, Process, Span, SpanId, SpanRef, SpanRefType, Tag, TraceId};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Allocate an id which is unique within this process, so that spans and traces
+/// built without explicit ids never collide with one another.
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Builds a [`Span`] without having to supply each of its fields positionally.
+///
+/// Unless overridden, a span starts a new trace, is given a fresh span id, starts
+/// at the same time as its parent (or now, if it has no parent) and lasts for one
+/// millisecond.
+#[derive(Clone, Debug)]
+pub struct SpanBuilder {
+    trace_id: Option<TraceId>,
+    span_id: Option<SpanId>,
+    parent: Option<(SpanId, i64)>,
+    operation_name: String,
+    references: Vec<SpanRef>,
+    flags: i32,
+    start_time: Option<i64>,
+    start_offset: Duration,
+    duration: i64,
+    tags: Vec<Tag>,
+    logs: Vec<Log>,
+}
+
+impl SpanBuilder {
+    pub fn new(operation_name: impl Into<String>) -> Self {
+        Self {
+            trace_id: None,
+            span_id: None,
+            parent: None,
+            operation_name: operation_name.into(),
+            references: Vec::new(),
+            flags: 1,
+            start_time: None,
+            start_offset: Duration::ZERO,
+            duration: 1_000,
+            tags: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn trace_id(mut self, trace_id: impl Into<TraceId>) -> Self {
+        self.trace_id = Some(trace_id.into());
+        self
+    }
+
+    pub fn span_id(mut self, span_id: impl Into<SpanId>) -> Self {
+        self.span_id = Some(span_id.into());
+        self
+    }
+
+    /// Make this span a child of `parent`, in the same trace.
+    pub fn child_of(mut self, parent: &Span) -> Self {
+        self.trace_id = Some(parent.trace_id());
+        self.parent = Some((parent.id(), parent.start_time));
+        self
+    }
+
+    /// Add a `FOLLOWS_FROM` reference to `span`, which may belong to another trace.
+    pub fn follows_from(mut self, span: &Span) -> Self {
+        self.references.push(SpanRef::new(
+            SpanRefType::FOLLOWS_FROM,
+            span.trace_id_low,
+            span.trace_id_high,
+            span.span_id,
+        ));
+        self
+    }
+
+    pub fn flags(mut self, flags: i32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the start time, in microseconds since the Unix epoch.
+    pub fn start_time(mut self, micros: i64) -> Self {
+        self.start_time = Some(micros);
+        self
+    }
+
+    /// Start the span this long after its parent, or after now if it has no
+    /// parent. Ignored if an explicit start time is set.
+    pub fn start_offset(mut self, offset: Duration) -> Self {
+        self.start_offset = offset;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration.as_micros() as i64;
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<OwnedTagValue>) -> Self {
+        self.tags.push(Tag::from_value(key, value));
+        self
+    }
+
+    /// Set the `span.kind` tag, e.g. `"server"` or `"client"`.
+    pub fn kind(self, kind: &str) -> Self {
+        self.tag("span.kind", kind)
+    }
+
+    /// Mark the span as failed, as the OpenTelemetry Jaeger exporter does.
+    pub fn error(self, description: &str) -> Self {
+        self.tag("otel.status_code", "ERROR")
+            .tag("error", true)
+            .tag("otel.status_description", description)
+    }
+
+    /// Record a log at the given time, in microseconds since the Unix epoch.
+    pub fn log<K, V>(mut self, timestamp: i64, fields: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<OwnedTagValue>,
+    {
+        let fields = fields
+            .into_iter()
+            .map(|(key, value)| Tag::from_value(key, value))
+            .collect();
+        self.logs.push(Log::new(timestamp, fields));
+        self
+    }
+
+    /// Record a named event at the given time, in microseconds since the Unix epoch.
+    pub fn event(self, timestamp: i64, name: &str) -> Self {
+        self.log(timestamp, vec![("event", name)])
+    }
+
+    pub fn build(self) -> Span {
+        let trace_id = self
+            .trace_id
+            .unwrap_or_else(|| TraceId(u128::from(next_id())));
+        let span_id = self.span_id.unwrap_or_else(|| SpanId(next_id()));
+        let start_offset = self.start_offset.as_micros() as i64;
+        let start_time = self.start_time.unwrap_or_else(|| {
+            self.parent
+                .map_or_else(now_micros, |(_, start_time)| start_time)
+                + start_offset
+        });
+
+        Span::new(
+            trace_id.jaeger_low(),
+            trace_id.jaeger_high(),
+            span_id.to_jaeger(),
+            self.parent
+                .map_or(0, |(parent_id, _)| parent_id.to_jaeger()),
+            self.operation_name,
+            Some(self.references).filter(|r| !r.is_empty()),
+            self.flags,
+            start_time,
+            self.duration,
+            Some(self.tags).filter(|t| !t.is_empty()),
+            Some(self.logs).filter(|l| !l.is_empty()),
+        )
+    }
+}
+
+/// Builds a [`Batch`] of spans reported by a single process.
+#[derive(Clone, Debug)]
+pub struct BatchBuilder {
+    service_name: String,
+    process_tags: Vec<Tag>,
+    spans: Vec<Span>,
+    seq_no: Option<i64>,
+}
+
+impl BatchBuilder {
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            process_tags: Vec::new(),
+            spans: Vec::new(),
+            seq_no: None,
+        }
+    }
+
+    pub fn process_tag(mut self, key: impl Into<String>, value: impl Into<OwnedTagValue>) -> Self {
+        self.process_tags.push(Tag::from_value(key, value));
+        self
+    }
+
+    pub fn span(mut self, span: Span) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn spans(mut self, spans: impl IntoIterator<Item = Span>) -> Self {
+        self.spans.extend(spans);
+        self
+    }
+
+    pub fn seq_no(mut self, seq_no: i64) -> Self {
+        self.seq_no = Some(seq_no);
+        self
+    }
+
+    pub fn build(self) -> Batch {
+        let process = Process::new(
+            self.service_name,
+            Some(self.process_tags).filter(|t| !t.is_empty()),
+        );
+        Batch::new(process, self.spans, self.seq_no, None)
+    }
+}
+
+fn now_micros() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_micros() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{SpanStatusCode, Trace};
+
+    #[test]
+    fn spans_get_fresh_ids_and_start_new_traces() {
+        let first = SpanBuilder::new("first").build();
+        let second = SpanBuilder::new("second").build();
+
+        assert_ne!(first.id(), second.id());
+        assert_ne!(first.trace_id(), second.trace_id());
+        assert_eq!(first.parent_id(), None);
+        assert_eq!(first.duration, 1_000);
+    }
+
+    #[test]
+    fn children_join_their_parents_trace_and_start_relative_to_it() {
+        let parent = SpanBuilder::new("parent")
+            .trace_id(TraceId(7))
+            .start_time(1_000_000)
+            .build();
+        let child = SpanBuilder::new("child")
+            .child_of(&parent)
+            .start_offset(Duration::from_millis(2))
+            .duration(Duration::from_millis(3))
+            .build();
+
+        assert_eq!(child.trace_id(), TraceId(7));
+        assert_eq!(child.parent_id(), Some(parent.id()));
+        assert_eq!(child.start_time, 1_002_000);
+        assert_eq!(child.duration, 3_000);
+
+        let trace = Trace::from_spans(vec![parent, child]).unwrap();
+        assert_eq!(trace.root().operation_name, "parent");
+        assert_eq!(trace.len(), 2);
+    }
+
+    #[test]
+    fn follows_from_references_may_point_at_other_traces() {
+        let producer = SpanBuilder::new("produce").build();
+        let consumer = SpanBuilder::new("consume").follows_from(&producer).build();
+
+        assert_ne!(consumer.trace_id(), producer.trace_id());
+        assert_eq!(consumer.parent_id(), None);
+        assert_eq!(consumer.follows_from().count(), 0);
+        let references = consumer.references.as_deref().unwrap();
+        assert_eq!(references[0].trace_id(), producer.trace_id());
+        assert_eq!(references[0].referenced_span_id(), producer.id());
+    }
+
+    #[test]
+    fn failed_spans_have_an_error_status() {
+        let span = SpanBuilder::new("op").error("stock unavailable").build();
+
+        let status = span.status();
+        assert_eq!(status.code, SpanStatusCode::Error);
+        assert_eq!(status.description.as_deref(), Some("stock unavailable"));
+    }
+
+    #[test]
+    fn batches_survive_thrift_encoding() {
+        let root = SpanBuilder::new("root").kind("server").build();
+        let child = SpanBuilder::new("child")
+            .child_of(&root)
+            .event(root.start_time, "cache miss")
+            .build();
+        let batch = BatchBuilder::new("cart_server")
+            .process_tag("hostname", "localhost")
+            .spans(vec![root, child])
+            .seq_no(3)
+            .build();
+
+        let binary = Batch::from_thrift_binary(&batch.to_thrift_binary().unwrap()).unwrap();
+        let compact = Batch::from_thrift_compact(&batch.to_thrift_compact().unwrap()).unwrap();
+
+        assert_eq!(binary, batch);
+        assert_eq!(compact, batch);
+        assert_eq!(batch.process.service_name, "cart_server");
+        assert_eq!(batch.seq_no, Some(3));
+    }
+}
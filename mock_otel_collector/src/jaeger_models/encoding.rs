@@ -0,0 +1,42 @@
+use thrift::protocol::{
+    TBinaryInputProtocol, TBinaryOutputProtocol, TCompactInputProtocol, TCompactOutputProtocol,
+    TOutputProtocol,
+};
+
+use super::Batch;
+
+/// The content type Jaeger collectors accept for Thrift binary-encoded batches.
+pub const THRIFT_BINARY_CONTENT_TYPE: &str = "application/vnd.apache.thrift.binary";
+/// The content type Jaeger collectors accept for Thrift compact-encoded batches.
+pub const THRIFT_COMPACT_CONTENT_TYPE: &str = "application/vnd.apache.thrift.compact";
+
+impl Batch {
+    /// Encode the batch using the Thrift binary protocol, as sent by the
+    /// OpenTelemetry Jaeger exporter to a collector's `/api/traces` endpoint.
+    pub fn to_thrift_binary(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let mut bytes = Vec::new();
+        let mut protocol = TBinaryOutputProtocol::new(&mut bytes, true);
+        self.write_to_out_protocol(&mut protocol)?;
+        protocol.flush()?;
+        Ok(bytes)
+    }
+
+    /// Encode the batch using the Thrift compact protocol, as sent by agents.
+    pub fn to_thrift_compact(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let mut bytes = Vec::new();
+        let mut protocol = TCompactOutputProtocol::new(&mut bytes);
+        self.write_to_out_protocol(&mut protocol)?;
+        protocol.flush()?;
+        Ok(bytes)
+    }
+
+    pub fn from_thrift_binary(bytes: &[u8]) -> Result<Batch, anyhow::Error> {
+        let mut protocol = TBinaryInputProtocol::new(bytes, false);
+        Ok(Batch::read_from_in_protocol(&mut protocol)?)
+    }
+
+    pub fn from_thrift_compact(bytes: &[u8]) -> Result<Batch, anyhow::Error> {
+        let mut protocol = TCompactInputProtocol::new(bytes);
+        Ok(Batch::read_from_in_protocol(&mut protocol)?)
+    }
+}
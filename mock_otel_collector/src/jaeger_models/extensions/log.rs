@@ -0,0 +1,141 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::jaeger_models::{Log, Span, Tag, TagValue};
+
+/// An exception recorded on a span, following the OpenTelemetry semantic
+/// conventions for exception events.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exception<'l> {
+    pub timestamp: SystemTime,
+    pub exception_type: Option<&'l str>,
+    pub message: Option<&'l str>,
+    pub stacktrace: Option<&'l str>,
+    pub escaped: Option<bool>,
+}
+
+impl Span {
+    /// The events logged on this span, ordered by timestamp.
+    pub fn events(&self) -> impl Iterator<Item = &Log> {
+        let mut logs: Vec<&Log> = self.logs.iter().flatten().collect();
+        logs.sort_by_key(|log| log.timestamp);
+        logs.into_iter()
+    }
+
+    /// Find the first event logged on this span with the given name.
+    pub fn find_event(&self, name: &str) -> Option<&Log> {
+        self.events().find(|log| log.event_name() == Some(name))
+    }
+
+    /// The exceptions recorded on this span, ordered by timestamp.
+    pub fn exceptions(&self) -> impl Iterator<Item = Exception<'_>> {
+        self.events().filter_map(|log| log.as_exception())
+    }
+}
+
+impl Log {
+    pub fn get_field(&self, key: &str) -> Option<&Tag> {
+        self.fields.iter().find(|t| t.key == key)
+    }
+
+    /// The name of the event. OpenTelemetry exporters record this in the `event`
+    /// field, while some other instrumentation uses `message`.
+    pub fn event_name(&self) -> Option<&str> {
+        self.str_field("event")
+            .or_else(|| self.str_field("message"))
+    }
+
+    /// The time at which the event was logged.
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_micros(self.timestamp.max(0) as u64)
+    }
+
+    /// Decode this event as an [`Exception`], if it is one: either it is named
+    /// `exception`, or it carries `exception.*` fields.
+    pub fn as_exception(&self) -> Option<Exception<'_>> {
+        let is_exception = self.event_name() == Some("exception")
+            || self.fields.iter().any(|t| t.key.starts_with("exception."));
+        if !is_exception {
+            return None;
+        }
+
+        Some(Exception {
+            timestamp: self.time(),
+            exception_type: self.str_field("exception.type"),
+            message: self.str_field("exception.message"),
+            stacktrace: self.str_field("exception.stacktrace"),
+            escaped: self
+                .get_field("exception.escaped")
+                .and_then(|t| match t.value().ok()? {
+                    TagValue::Bool(escaped) => Some(escaped),
+                    TagValue::String(escaped) => escaped.parse().ok(),
+                    _ => None,
+                }),
+        })
+    }
+
+    fn str_field(&self, key: &str) -> Option<&str> {
+        match self.get_field(key)?.value().ok()? {
+            TagValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{OwnedTagValue, SpanBuilder};
+
+    #[test]
+    fn events_are_ordered_by_timestamp() {
+        let span = SpanBuilder::new("checkout")
+            .event(3, "paid")
+            .log(1, vec![("message", "started")])
+            .event(2, "reserved")
+            .build();
+
+        let names: Vec<_> = span.events().map(|log| log.event_name().unwrap()).collect();
+        assert_eq!(names, vec!["started", "reserved", "paid"]);
+        assert_eq!(span.find_event("reserved").unwrap().timestamp, 2);
+        assert!(span.find_event("shipped").is_none());
+    }
+
+    #[test]
+    fn exception_events_expose_their_fields() {
+        let span = SpanBuilder::new("checkout")
+            .event(1, "reserved")
+            .log(
+                2,
+                vec![
+                    ("event", OwnedTagValue::from("exception")),
+                    ("exception.type", "StockError".into()),
+                    ("exception.message", "out of stock".into()),
+                    ("exception.stacktrace", "at checkout".into()),
+                    ("exception.escaped", true.into()),
+                ],
+            )
+            .log(3, vec![("exception.escaped", "false")])
+            .build();
+
+        let exceptions: Vec<_> = span.exceptions().collect();
+        assert_eq!(
+            exceptions,
+            vec![
+                Exception {
+                    timestamp: UNIX_EPOCH + Duration::from_micros(2),
+                    exception_type: Some("StockError"),
+                    message: Some("out of stock"),
+                    stacktrace: Some("at checkout"),
+                    escaped: Some(true),
+                },
+                Exception {
+                    timestamp: UNIX_EPOCH + Duration::from_micros(3),
+                    exception_type: None,
+                    message: None,
+                    stacktrace: None,
+                    escaped: Some(false),
+                },
+            ]
+        );
+    }
+}
@@ -1,5 +1,11 @@
+mod log;
+mod semantic_conventions;
 mod span;
+mod status;
 mod tag;
 
+pub use log::*;
+pub use semantic_conventions::*;
 pub use span::*;
+pub use status::*;
 pub use tag::*;
@@ -0,0 +1,329 @@
+//! Typed views over [`Span`]s following the OpenTelemetry semantic conventions
+//! for HTTP, database and messaging operations.
+//!
+//! Views are built leniently: every attribute is optional, and each view can
+//! report which of the attributes required by the conventions are missing.
+use crate::jaeger_models::{Span, TagValue};
+
+impl Span {
+    /// The kind of the span (`server`, `client`, `producer`, `consumer` or
+    /// `internal`), as recorded in the `span.kind` tag.
+    pub fn span_kind(&self) -> Option<&str> {
+        str_tag(self, "span.kind")
+    }
+
+    /// View this span as an HTTP server span, if it is a server span carrying
+    /// HTTP attributes.
+    pub fn as_http_server(&self) -> Option<HttpServerSpan<'_>> {
+        (self.span_kind() == Some("server") && has_tag_with_prefix(self, "http."))
+            .then(|| HttpServerSpan::from_span(self))
+    }
+
+    /// View this span as an HTTP client span, if it is a client span carrying
+    /// HTTP attributes.
+    pub fn as_http_client(&self) -> Option<HttpClientSpan<'_>> {
+        (self.span_kind() == Some("client") && has_tag_with_prefix(self, "http."))
+            .then(|| HttpClientSpan::from_span(self))
+    }
+
+    /// View this span as a database span, if it carries database attributes.
+    pub fn as_db(&self) -> Option<DbSpan<'_>> {
+        has_tag_with_prefix(self, "db.").then(|| DbSpan::from_span(self))
+    }
+
+    /// View this span as a messaging span, if it carries messaging attributes.
+    pub fn as_messaging(&self) -> Option<MessagingSpan<'_>> {
+        has_tag_with_prefix(self, "messaging.").then(|| MessagingSpan::from_span(self))
+    }
+}
+
+/// An incoming HTTP request handled by a server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpServerSpan<'s> {
+    pub method: Option<&'s str>,
+    pub route: Option<&'s str>,
+    pub target: Option<&'s str>,
+    pub url: Option<&'s str>,
+    pub scheme: Option<&'s str>,
+    pub host: Option<&'s str>,
+    pub server_name: Option<&'s str>,
+    pub flavor: Option<&'s str>,
+    pub user_agent: Option<&'s str>,
+    pub client_ip: Option<&'s str>,
+    pub host_port: Option<i64>,
+    pub status_code: Option<i64>,
+}
+
+impl<'s> HttpServerSpan<'s> {
+    pub fn from_span(span: &'s Span) -> Self {
+        Self {
+            method: str_tag(span, "http.method"),
+            route: str_tag(span, "http.route"),
+            target: str_tag(span, "http.target"),
+            url: str_tag(span, "http.url"),
+            scheme: str_tag(span, "http.scheme"),
+            host: str_tag(span, "http.host"),
+            server_name: str_tag(span, "http.server_name"),
+            flavor: str_tag(span, "http.flavor"),
+            user_agent: str_tag(span, "http.user_agent"),
+            client_ip: str_tag(span, "http.client_ip"),
+            host_port: int_tag(span, "net.host.port"),
+            status_code: int_tag(span, "http.status_code"),
+        }
+    }
+
+    /// The attributes required by the conventions which are missing from the span.
+    /// Servers must record the method, the status code, and either the full URL
+    /// or the target.
+    pub fn missing_required_attributes(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.method.is_none() {
+            missing.push("http.method");
+        }
+        if self.target.is_none() && self.url.is_none() {
+            missing.push("http.target");
+        }
+        if self.status_code.is_none() {
+            missing.push("http.status_code");
+        }
+        missing
+    }
+}
+
+/// An outgoing HTTP request made by a client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpClientSpan<'s> {
+    pub method: Option<&'s str>,
+    pub url: Option<&'s str>,
+    pub target: Option<&'s str>,
+    pub scheme: Option<&'s str>,
+    pub host: Option<&'s str>,
+    pub flavor: Option<&'s str>,
+    pub user_agent: Option<&'s str>,
+    pub peer_name: Option<&'s str>,
+    pub peer_ip: Option<&'s str>,
+    pub peer_port: Option<i64>,
+    pub status_code: Option<i64>,
+}
+
+impl<'s> HttpClientSpan<'s> {
+    pub fn from_span(span: &'s Span) -> Self {
+        Self {
+            method: str_tag(span, "http.method"),
+            url: str_tag(span, "http.url"),
+            target: str_tag(span, "http.target"),
+            scheme: str_tag(span, "http.scheme"),
+            host: str_tag(span, "http.host"),
+            flavor: str_tag(span, "http.flavor"),
+            user_agent: str_tag(span, "http.user_agent"),
+            peer_name: str_tag(span, "net.peer.name"),
+            peer_ip: str_tag(span, "net.peer.ip"),
+            peer_port: int_tag(span, "net.peer.port"),
+            status_code: int_tag(span, "http.status_code"),
+        }
+    }
+
+    /// The attributes required by the conventions which are missing from the span.
+    /// Clients must record the method, the status code of any response received,
+    /// and either the full URL or enough to reconstruct it (scheme, host and target).
+    pub fn missing_required_attributes(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.method.is_none() {
+            missing.push("http.method");
+        }
+        if self.url.is_none() {
+            if self.scheme.is_none() {
+                missing.push("http.scheme");
+            }
+            if self.host.is_none() && self.peer_name.is_none() {
+                missing.push("http.host");
+            }
+            if self.target.is_none() {
+                missing.push("http.target");
+            }
+        }
+        if self.status_code.is_none() {
+            missing.push("http.status_code");
+        }
+        missing
+    }
+}
+
+/// A call made to a database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DbSpan<'s> {
+    pub system: Option<&'s str>,
+    pub connection_string: Option<&'s str>,
+    pub user: Option<&'s str>,
+    pub name: Option<&'s str>,
+    pub statement: Option<&'s str>,
+    pub operation: Option<&'s str>,
+    pub peer_name: Option<&'s str>,
+    pub peer_ip: Option<&'s str>,
+    pub peer_port: Option<i64>,
+}
+
+impl<'s> DbSpan<'s> {
+    pub fn from_span(span: &'s Span) -> Self {
+        Self {
+            system: str_tag(span, "db.system"),
+            connection_string: str_tag(span, "db.connection_string"),
+            user: str_tag(span, "db.user"),
+            name: str_tag(span, "db.name"),
+            statement: str_tag(span, "db.statement"),
+            operation: str_tag(span, "db.operation"),
+            peer_name: str_tag(span, "net.peer.name"),
+            peer_ip: str_tag(span, "net.peer.ip"),
+            peer_port: int_tag(span, "net.peer.port"),
+        }
+    }
+
+    /// The attributes required by the conventions which are missing from the span.
+    /// Database calls must record the database system and the peer they were made to.
+    pub fn missing_required_attributes(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.system.is_none() {
+            missing.push("db.system");
+        }
+        if self.peer_name.is_none() && self.peer_ip.is_none() {
+            missing.push("net.peer.name");
+        }
+        missing
+    }
+}
+
+/// A message sent to, or received from, a messaging system.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessagingSpan<'s> {
+    pub system: Option<&'s str>,
+    pub destination: Option<&'s str>,
+    pub destination_kind: Option<&'s str>,
+    pub operation: Option<&'s str>,
+    pub protocol: Option<&'s str>,
+    pub url: Option<&'s str>,
+    pub message_id: Option<&'s str>,
+    pub conversation_id: Option<&'s str>,
+    pub peer_name: Option<&'s str>,
+    pub peer_port: Option<i64>,
+}
+
+impl<'s> MessagingSpan<'s> {
+    pub fn from_span(span: &'s Span) -> Self {
+        Self {
+            system: str_tag(span, "messaging.system"),
+            destination: str_tag(span, "messaging.destination"),
+            destination_kind: str_tag(span, "messaging.destination_kind"),
+            operation: str_tag(span, "messaging.operation"),
+            protocol: str_tag(span, "messaging.protocol"),
+            url: str_tag(span, "messaging.url"),
+            message_id: str_tag(span, "messaging.message_id"),
+            conversation_id: str_tag(span, "messaging.conversation_id"),
+            peer_name: str_tag(span, "net.peer.name"),
+            peer_port: int_tag(span, "net.peer.port"),
+        }
+    }
+
+    /// The attributes required by the conventions which are missing from the span.
+    /// Messaging operations must record the messaging system and the destination.
+    pub fn missing_required_attributes(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.system.is_none() {
+            missing.push("messaging.system");
+        }
+        if self.destination.is_none() {
+            missing.push("messaging.destination");
+        }
+        missing
+    }
+}
+
+fn has_tag_with_prefix(span: &Span, prefix: &str) -> bool {
+    span.tags
+        .iter()
+        .flatten()
+        .any(|tag| tag.key.starts_with(prefix))
+}
+
+fn str_tag<'s>(span: &'s Span, key: &str) -> Option<&'s str> {
+    match span.get_tag(key)?.value().ok()? {
+        TagValue::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Integer attributes are sometimes recorded as strings by instrumentation, so
+/// both representations are accepted.
+fn int_tag(span: &Span, key: &str) -> Option<i64> {
+    match span.get_tag(key)?.value().ok()? {
+        TagValue::Long(value) => Some(value),
+        TagValue::String(value) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jaeger_models::SpanBuilder;
+
+    #[test]
+    fn http_server_spans_read_the_http_attributes() {
+        let span = SpanBuilder::new("GET /cart")
+            .kind("server")
+            .tag("http.method", "GET")
+            .tag("http.route", "/cart")
+            .tag("http.target", "/cart?id=1")
+            .tag("http.host", "localhost:8080")
+            .tag("net.host.port", 8080i64)
+            .tag("http.status_code", "200")
+            .build();
+
+        let http = span.as_http_server().unwrap();
+        assert_eq!(http.method, Some("GET"));
+        assert_eq!(http.route, Some("/cart"));
+        assert_eq!(http.target, Some("/cart?id=1"));
+        assert_eq!(http.host, Some("localhost:8080"));
+        assert_eq!(http.host_port, Some(8080));
+        assert_eq!(http.status_code, Some(200));
+        assert!(http.missing_required_attributes().is_empty());
+        assert!(span.as_http_client().is_none());
+    }
+
+    #[test]
+    fn http_client_spans_report_missing_attributes() {
+        let span = SpanBuilder::new("HTTP GET")
+            .kind("client")
+            .tag("http.method", "GET")
+            .tag("net.peer.name", "stock-service")
+            .tag("net.peer.port", 9000i64)
+            .build();
+
+        let http = span.as_http_client().unwrap();
+        assert_eq!(http.peer_name, Some("stock-service"));
+        assert_eq!(http.peer_port, Some(9000));
+        assert_eq!(
+            http.missing_required_attributes(),
+            vec!["http.scheme", "http.target", "http.status_code"]
+        );
+        assert!(span.as_http_server().is_none());
+    }
+
+    #[test]
+    fn db_spans_read_the_db_attributes() {
+        let span = SpanBuilder::new("SELECT carts")
+            .kind("client")
+            .tag("db.system", "postgresql")
+            .tag("db.name", "carts")
+            .tag("db.statement", "SELECT * FROM carts")
+            .tag("db.operation", "SELECT")
+            .build();
+
+        let db = span.as_db().unwrap();
+        assert_eq!(db.system, Some("postgresql"));
+        assert_eq!(db.name, Some("carts"));
+        assert_eq!(db.statement, Some("SELECT * FROM carts"));
+        assert_eq!(db.operation, Some("SELECT"));
+        assert_eq!(db.missing_required_attributes(), vec!["net.peer.name"]);
+        assert!(span.as_http_client().is_none());
+        assert!(span.as_messaging().is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::jaeger_models::{Span, Tag};
+use crate::jaeger_models::{Span, SpanId, SpanRef, SpanRefType, Tag};
 
 impl Span {
     pub fn get_tag(&self, key: &str) -> Option<&Tag> {
@@ -6,7 +6,42 @@ impl Span {
         tag
     }
 
-    pub fn hex_trace_id(&self) -> String {
-        format!("{:016x}{:016x}", &self.trace_id_high, &self.trace_id_low)
+    /// The references held by this span which point at spans in the same trace.
+    pub fn references_within_trace(&self) -> impl Iterator<Item = &SpanRef> {
+        self.references
+            .iter()
+            .flatten()
+            .filter(move |r| r.is_within_trace_of(self))
+    }
+
+    /// The id of this span's parent, or `None` if this is a root span.
+    ///
+    /// A `CHILD_OF` reference takes precedence, followed by the legacy
+    /// `parent_span_id` field. Failing both, a `FOLLOWS_FROM` reference is
+    /// treated as the parent, in line with how Jaeger itself assembles traces.
+    pub fn parent_id(&self) -> Option<SpanId> {
+        let reference_of_type = |ref_type| {
+            self.references_within_trace()
+                .find(|r| r.ref_type == ref_type)
+                .map(|r| r.referenced_span_id())
+        };
+
+        reference_of_type(SpanRefType::CHILD_OF)
+            .or_else(|| Some(SpanId::from_jaeger(self.parent_span_id)).filter(|id| id.0 != 0))
+            .or_else(|| reference_of_type(SpanRefType::FOLLOWS_FROM))
+    }
+
+    /// The `FOLLOWS_FROM` references held by this span which point at spans in
+    /// the same trace.
+    pub fn follows_from(&self) -> impl Iterator<Item = &SpanRef> {
+        self.references_within_trace()
+            .filter(|r| r.ref_type == SpanRefType::FOLLOWS_FROM)
+    }
+}
+
+impl SpanRef {
+    /// Whether this reference points at a span within the same trace as `span`.
+    pub fn is_within_trace_of(&self, span: &Span) -> bool {
+        self.trace_id() == span.trace_id()
     }
 }
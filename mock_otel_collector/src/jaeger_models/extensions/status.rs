@@ -0,0 +1,135 @@
+use crate::jaeger_models::{Span, TagValue, Trace, TraceSpan};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpanStatusCode {
+    Unset,
+    Ok,
+    Error,
+}
+
+/// The status of a span, normalised from the various ways it may be encoded
+/// in Jaeger tags.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanStatus {
+    pub code: SpanStatusCode,
+    pub description: Option<String>,
+}
+
+impl SpanStatus {
+    pub fn is_error(&self) -> bool {
+        self.code == SpanStatusCode::Error
+    }
+}
+
+impl Span {
+    /// The status of this span.
+    ///
+    /// An explicit `otel.status_code` tag takes precedence; failing that, an
+    /// `error=true` tag marks the span as failed. The description is read from
+    /// `otel.status_description`, or `otel.status_message` if that is absent.
+    pub fn status(&self) -> SpanStatus {
+        let code = self
+            .get_tag("otel.status_code")
+            .and_then(|tag| match tag.value().ok()? {
+                TagValue::String(code) => match code.to_ascii_uppercase().as_str() {
+                    "OK" => Some(SpanStatusCode::Ok),
+                    "ERROR" => Some(SpanStatusCode::Error),
+                    "UNSET" => Some(SpanStatusCode::Unset),
+                    _ => None,
+                },
+                TagValue::Long(0) => Some(SpanStatusCode::Unset),
+                TagValue::Long(1) => Some(SpanStatusCode::Ok),
+                TagValue::Long(2) => Some(SpanStatusCode::Error),
+                _ => None,
+            })
+            .or_else(|| match self.get_tag("error")?.value().ok()? {
+                TagValue::Bool(true) => Some(SpanStatusCode::Error),
+                TagValue::String(is_error) if is_error.eq_ignore_ascii_case("true") => {
+                    Some(SpanStatusCode::Error)
+                }
+                _ => None,
+            })
+            .unwrap_or(SpanStatusCode::Unset);
+
+        let description = ["otel.status_description", "otel.status_message"]
+            .iter()
+            .find_map(|key| match self.get_tag(key)?.value().ok()? {
+                TagValue::String(description) => Some(description.to_owned()),
+                _ => None,
+            });
+
+        SpanStatus { code, description }
+    }
+}
+
+impl Trace {
+    /// The spans in this trace whose status is [`SpanStatusCode::Error`], depth-first.
+    pub fn error_spans(&self) -> impl Iterator<Item = TraceSpan<'_>> {
+        self.descendants().filter(|span| span.status().is_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{OwnedTagValue, SpanBuilder};
+
+    fn status(builder: SpanBuilder) -> SpanStatus {
+        builder.build().status()
+    }
+
+    #[test]
+    fn spans_without_status_tags_are_unset() {
+        assert_eq!(
+            status(SpanBuilder::new("checkout")),
+            SpanStatus {
+                code: SpanStatusCode::Unset,
+                description: None,
+            }
+        );
+    }
+
+    #[test]
+    fn status_codes_are_read_from_strings_or_integers() {
+        let code = |value: OwnedTagValue| {
+            status(SpanBuilder::new("checkout").tag("otel.status_code", value)).code
+        };
+        assert_eq!(code("ok".into()), SpanStatusCode::Ok);
+        assert_eq!(code("ERROR".into()), SpanStatusCode::Error);
+        assert_eq!(code(0i64.into()), SpanStatusCode::Unset);
+        assert_eq!(code(1i64.into()), SpanStatusCode::Ok);
+        assert_eq!(code(2i64.into()), SpanStatusCode::Error);
+    }
+
+    #[test]
+    fn error_tags_mark_spans_as_failed() {
+        assert!(status(SpanBuilder::new("checkout").tag("error", true)).is_error());
+        assert!(status(SpanBuilder::new("checkout").tag("error", "True")).is_error());
+        assert!(!status(SpanBuilder::new("checkout").tag("error", false)).is_error());
+    }
+
+    #[test]
+    fn explicit_status_codes_take_precedence_over_error_tags() {
+        let span = SpanBuilder::new("checkout")
+            .tag("otel.status_code", "OK")
+            .tag("error", true);
+        assert_eq!(status(span).code, SpanStatusCode::Ok);
+    }
+
+    #[test]
+    fn descriptions_fall_back_to_the_status_message() {
+        let span = SpanBuilder::new("checkout").error("out of stock");
+        assert_eq!(
+            status(span),
+            SpanStatus {
+                code: SpanStatusCode::Error,
+                description: Some("out of stock".to_owned()),
+            }
+        );
+
+        let span = SpanBuilder::new("checkout")
+            .tag("error", true)
+            .tag("otel.status_message", "timed out");
+        assert_eq!(status(span).description.as_deref(), Some("timed out"));
+    }
+}
@@ -1,5 +1,9 @@
 use crate::jaeger_models::{Tag, TagType};
 use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use thrift::OrderedFloat;
 
 #[derive(PartialEq, Debug)]
 pub enum TagValue<'t> {
@@ -40,3 +44,283 @@ impl Tag {
         })
     }
 }
+
+impl<'t> Display for TagValue<'t> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TagValue::String(value) => write!(f, "{}", value),
+            TagValue::Double(value) => write!(f, "{}", value),
+            TagValue::Bool(value) => write!(f, "{}", value),
+            TagValue::Long(value) => write!(f, "{}", value),
+            TagValue::Binary(value) => value.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
+
+/// An owned counterpart to [`TagValue`], which can be stored, serialised, and
+/// compared directly against the values of received tags.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OwnedTagValue {
+    String(String),
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    Binary(Vec<u8>),
+}
+
+impl Tag {
+    /// Build a tag with the given key and value, setting its type accordingly.
+    pub fn from_value(key: impl Into<String>, value: impl Into<OwnedTagValue>) -> Tag {
+        let key = key.into();
+        match value.into() {
+            OwnedTagValue::String(v) => Tag::new(key, TagType::STRING, v, None, None, None, None),
+            OwnedTagValue::Bool(v) => Tag::new(key, TagType::BOOL, None, None, v, None, None),
+            OwnedTagValue::Long(v) => Tag::new(key, TagType::LONG, None, None, None, v, None),
+            OwnedTagValue::Double(v) => Tag::new(
+                key,
+                TagType::DOUBLE,
+                None,
+                OrderedFloat(v),
+                None,
+                None,
+                None,
+            ),
+            OwnedTagValue::Binary(v) => Tag::new(key, TagType::BINARY, None, None, None, None, v),
+        }
+    }
+
+    pub fn owned_value(&self) -> Result<OwnedTagValue, anyhow::Error> {
+        self.value().map(OwnedTagValue::from)
+    }
+}
+
+impl<'t> TagValue<'t> {
+    pub fn to_owned_value(&self) -> OwnedTagValue {
+        match self {
+            TagValue::String(v) => OwnedTagValue::String((*v).to_owned()),
+            TagValue::Double(v) => OwnedTagValue::Double(*v),
+            TagValue::Bool(v) => OwnedTagValue::Bool(*v),
+            TagValue::Long(v) => OwnedTagValue::Long(*v),
+            TagValue::Binary(v) => OwnedTagValue::Binary((*v).clone()),
+        }
+    }
+}
+
+impl OwnedTagValue {
+    pub fn as_tag_value(&self) -> TagValue<'_> {
+        match self {
+            OwnedTagValue::String(v) => TagValue::String(v),
+            OwnedTagValue::Double(v) => TagValue::Double(*v),
+            OwnedTagValue::Bool(v) => TagValue::Bool(*v),
+            OwnedTagValue::Long(v) => TagValue::Long(*v),
+            OwnedTagValue::Binary(v) => TagValue::Binary(v),
+        }
+    }
+}
+
+impl Display for OwnedTagValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.as_tag_value().fmt(f)
+    }
+}
+
+impl<'t> From<TagValue<'t>> for OwnedTagValue {
+    fn from(value: TagValue<'t>) -> Self {
+        value.to_owned_value()
+    }
+}
+
+impl<'t> PartialEq<TagValue<'t>> for OwnedTagValue {
+    fn eq(&self, other: &TagValue<'t>) -> bool {
+        self.as_tag_value() == *other
+    }
+}
+
+impl<'t> PartialEq<OwnedTagValue> for TagValue<'t> {
+    fn eq(&self, other: &OwnedTagValue) -> bool {
+        *self == other.as_tag_value()
+    }
+}
+
+impl From<&str> for OwnedTagValue {
+    fn from(value: &str) -> Self {
+        OwnedTagValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for OwnedTagValue {
+    fn from(value: String) -> Self {
+        OwnedTagValue::String(value)
+    }
+}
+
+impl From<bool> for OwnedTagValue {
+    fn from(value: bool) -> Self {
+        OwnedTagValue::Bool(value)
+    }
+}
+
+impl From<i64> for OwnedTagValue {
+    fn from(value: i64) -> Self {
+        OwnedTagValue::Long(value)
+    }
+}
+
+impl From<i32> for OwnedTagValue {
+    fn from(value: i32) -> Self {
+        OwnedTagValue::Long(value.into())
+    }
+}
+
+impl From<u32> for OwnedTagValue {
+    fn from(value: u32) -> Self {
+        OwnedTagValue::Long(value.into())
+    }
+}
+
+impl From<u16> for OwnedTagValue {
+    fn from(value: u16) -> Self {
+        OwnedTagValue::Long(value.into())
+    }
+}
+
+impl From<f64> for OwnedTagValue {
+    fn from(value: f64) -> Self {
+        OwnedTagValue::Double(value)
+    }
+}
+
+impl From<Vec<u8>> for OwnedTagValue {
+    fn from(value: Vec<u8>) -> Self {
+        OwnedTagValue::Binary(value)
+    }
+}
+
+impl TryFrom<&Tag> for OwnedTagValue {
+    type Error = anyhow::Error;
+
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        tag.owned_value()
+    }
+}
+
+impl TryFrom<&Tag> for i64 {
+    type Error = anyhow::Error;
+
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        match tag.value()? {
+            TagValue::Long(value) => Ok(value),
+            other => bail!("Tag {} was not a long: {:?}", tag.key, other),
+        }
+    }
+}
+
+impl TryFrom<&Tag> for f64 {
+    type Error = anyhow::Error;
+
+    /// Long values are widened to doubles, since instrumentation is not always
+    /// consistent about which it records.
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        match tag.value()? {
+            TagValue::Double(value) => Ok(value),
+            TagValue::Long(value) => Ok(value as f64),
+            other => bail!("Tag {} was not a double: {:?}", tag.key, other),
+        }
+    }
+}
+
+impl TryFrom<&Tag> for bool {
+    type Error = anyhow::Error;
+
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        match tag.value()? {
+            TagValue::Bool(value) => Ok(value),
+            other => bail!("Tag {} was not a bool: {:?}", tag.key, other),
+        }
+    }
+}
+
+impl TryFrom<&Tag> for String {
+    type Error = anyhow::Error;
+
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        match tag.value()? {
+            TagValue::String(value) => Ok(value.to_owned()),
+            other => bail!("Tag {} was not a string: {:?}", tag.key, other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned_values_serialise_untagged() {
+        let values = vec![
+            OwnedTagValue::from("GET"),
+            OwnedTagValue::from(true),
+            OwnedTagValue::from(200i64),
+            OwnedTagValue::from(1.0),
+            OwnedTagValue::from(vec![1u8, 2]),
+        ];
+        let json = serde_json::to_string(&values).unwrap();
+        assert_eq!(json, r#"["GET",true,200,1.0,[1,2]]"#);
+        assert_eq!(
+            serde_json::from_str::<Vec<OwnedTagValue>>(&json).unwrap(),
+            values
+        );
+    }
+
+    #[test]
+    fn integers_and_floats_keep_their_types_when_deserialised() {
+        let parse = |json| serde_json::from_str::<OwnedTagValue>(json).unwrap();
+        assert_eq!(parse("1"), OwnedTagValue::Long(1));
+        assert_eq!(parse("-1"), OwnedTagValue::Long(-1));
+        assert_eq!(parse("1.0"), OwnedTagValue::Double(1.0));
+        assert_eq!(parse("1e3"), OwnedTagValue::Double(1000.0));
+        assert_eq!(parse(r#""1""#), OwnedTagValue::String("1".to_owned()));
+    }
+
+    #[test]
+    fn owned_values_are_read_from_tags() {
+        for value in [
+            OwnedTagValue::from("GET"),
+            OwnedTagValue::from(false),
+            OwnedTagValue::from(-3i64),
+            OwnedTagValue::from(0.5),
+            OwnedTagValue::from(vec![0xffu8]),
+        ] {
+            let tag = Tag::from_value("key", value.clone());
+            assert_eq!(OwnedTagValue::try_from(&tag).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn tags_without_a_value_for_their_type_are_rejected() {
+        let tag = Tag::new(
+            "key".to_owned(),
+            TagType::LONG,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(OwnedTagValue::try_from(&tag).is_err());
+
+        let tag = Tag::new("key".to_owned(), TagType(42), None, None, None, None, None);
+        assert!(OwnedTagValue::try_from(&tag).is_err());
+    }
+
+    #[test]
+    fn typed_values_are_read_from_tags() {
+        assert_eq!(i64::try_from(&Tag::from_value("n", 3i64)).unwrap(), 3);
+        assert_eq!(f64::try_from(&Tag::from_value("n", 3i64)).unwrap(), 3.0);
+        assert!(i64::try_from(&Tag::from_value("n", 3.0)).is_err());
+        assert!(bool::try_from(&Tag::from_value("b", true)).unwrap());
+        assert_eq!(String::try_from(&Tag::from_value("s", "x")).unwrap(), "x");
+        assert!(String::try_from(&Tag::from_value("s", 1i64)).is_err());
+    }
+}
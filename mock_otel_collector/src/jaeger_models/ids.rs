@@ -0,0 +1,224 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+
+use super::{Span, SpanRef};
+
+/// A 128-bit trace id.
+///
+/// Jaeger splits trace ids into two signed 64-bit halves; this type reassembles
+/// them so they can be compared, formatted and converted directly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TraceId(pub u128);
+
+/// A 64-bit span id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpanId(pub u64);
+
+impl TraceId {
+    /// Assemble a trace id from the halves used by Jaeger.
+    pub fn from_jaeger(high: i64, low: i64) -> Self {
+        Self(((high as u64 as u128) << 64) | (low as u64 as u128))
+    }
+
+    /// The high half of the trace id, as used by Jaeger.
+    pub fn jaeger_high(&self) -> i64 {
+        (self.0 >> 64) as u64 as i64
+    }
+
+    /// The low half of the trace id, as used by Jaeger.
+    pub fn jaeger_low(&self) -> i64 {
+        self.0 as u64 as i64
+    }
+
+    /// Parse a trace id from up to 32 hexadecimal digits.
+    pub fn from_hex(hex: &str) -> Result<Self, anyhow::Error> {
+        if !is_hex(hex, 32) {
+            bail!("Invalid trace id: {}", hex);
+        }
+        let id =
+            u128::from_str_radix(hex, 16).with_context(|| format!("Invalid trace id: {}", hex))?;
+        Ok(Self(id))
+    }
+
+    /// Format the trace id as 32 lowercase hexadecimal digits.
+    pub fn to_hex(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+impl SpanId {
+    /// Convert a span id as used by Jaeger.
+    pub fn from_jaeger(id: i64) -> Self {
+        Self(id as u64)
+    }
+
+    /// The span id, as used by Jaeger.
+    pub fn to_jaeger(&self) -> i64 {
+        self.0 as i64
+    }
+
+    /// Parse a span id from up to 16 hexadecimal digits.
+    pub fn from_hex(hex: &str) -> Result<Self, anyhow::Error> {
+        if !is_hex(hex, 16) {
+            bail!("Invalid span id: {}", hex);
+        }
+        let id =
+            u64::from_str_radix(hex, 16).with_context(|| format!("Invalid span id: {}", hex))?;
+        Ok(Self(id))
+    }
+
+    /// Format the span id as 16 lowercase hexadecimal digits.
+    pub fn to_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// `from_str_radix` accepts a leading `+`, so the digits are checked up front.
+fn is_hex(hex: &str, max_digits: usize) -> bool {
+    !hex.is_empty() && hex.len() <= max_digits && hex.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+impl Display for TraceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl Display for SpanId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for TraceId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl FromStr for SpanId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl From<opentelemetry::trace::TraceId> for TraceId {
+    fn from(id: opentelemetry::trace::TraceId) -> Self {
+        Self(id.to_u128())
+    }
+}
+
+impl From<TraceId> for opentelemetry::trace::TraceId {
+    fn from(id: TraceId) -> Self {
+        opentelemetry::trace::TraceId::from_u128(id.0)
+    }
+}
+
+impl From<opentelemetry::trace::SpanId> for SpanId {
+    fn from(id: opentelemetry::trace::SpanId) -> Self {
+        Self(id.to_u64())
+    }
+}
+
+impl From<SpanId> for opentelemetry::trace::SpanId {
+    fn from(id: SpanId) -> Self {
+        opentelemetry::trace::SpanId::from_u64(id.0)
+    }
+}
+
+impl Span {
+    pub fn trace_id(&self) -> TraceId {
+        TraceId::from_jaeger(self.trace_id_high, self.trace_id_low)
+    }
+
+    pub fn id(&self) -> SpanId {
+        SpanId::from_jaeger(self.span_id)
+    }
+}
+
+impl SpanRef {
+    pub fn trace_id(&self) -> TraceId {
+        TraceId::from_jaeger(self.trace_id_high, self.trace_id_low)
+    }
+
+    /// The id of the span being referenced.
+    pub fn referenced_span_id(&self) -> SpanId {
+        SpanId::from_jaeger(self.span_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    #[test]
+    fn hex_ids_round_trip_with_leading_zeros() {
+        let trace_id: TraceId = "00000000000000000000000000000abc".parse().unwrap();
+        assert_eq!(trace_id, TraceId(0xabc));
+        assert_eq!(trace_id.to_hex(), "00000000000000000000000000000abc");
+        assert_eq!(trace_id.to_string(), trace_id.to_hex());
+
+        let span_id: SpanId = "000000000000000f".parse().unwrap();
+        assert_eq!(span_id, SpanId(0xf));
+        assert_eq!(span_id.to_hex(), "000000000000000f");
+        assert_eq!(span_id.to_string(), span_id.to_hex());
+    }
+
+    #[test]
+    fn short_and_uppercase_hex_ids_are_accepted() {
+        assert_eq!(TraceId::from_hex("ABC").unwrap(), TraceId(0xabc));
+        assert_eq!(SpanId::from_hex("1").unwrap(), SpanId(1));
+        assert_eq!(
+            TraceId::from_hex(&"f".repeat(32)).unwrap(),
+            TraceId(u128::MAX)
+        );
+        assert_eq!(SpanId::from_hex(&"f".repeat(16)).unwrap(), SpanId(u64::MAX));
+    }
+
+    #[test]
+    fn invalid_hex_ids_are_rejected() {
+        for hex in ["", "+1", "-1", "0x1", "xyz", " 1"] {
+            assert!(TraceId::from_hex(hex).is_err(), "{:?}", hex);
+            assert!(SpanId::from_hex(hex).is_err(), "{:?}", hex);
+        }
+        assert!(TraceId::from_hex(&"0".repeat(33)).is_err());
+        assert!(SpanId::from_hex(&"0".repeat(17)).is_err());
+    }
+
+    #[test]
+    fn jaeger_halves_are_reinterpreted_as_unsigned() {
+        let trace_id = TraceId::from_jaeger(-1, i64::MIN);
+        assert_eq!(trace_id.to_hex(), "ffffffffffffffff8000000000000000");
+        assert_eq!(trace_id.jaeger_high(), -1);
+        assert_eq!(trace_id.jaeger_low(), i64::MIN);
+
+        let trace_id = TraceId::from_jaeger(0, -1);
+        assert_eq!(trace_id, TraceId(u64::MAX as u128));
+        assert_eq!(trace_id.jaeger_high(), 0);
+        assert_eq!(trace_id.jaeger_low(), -1);
+
+        let span_id = SpanId::from_jaeger(-2);
+        assert_eq!(span_id.to_hex(), "fffffffffffffffe");
+        assert_eq!(span_id.to_jaeger(), -2);
+    }
+
+    #[test]
+    fn spans_expose_their_ids() {
+        let trace_id = TraceId(1 << 127 | 5);
+        let span = SpanBuilder::new("checkout")
+            .trace_id(trace_id)
+            .span_id(SpanId(u64::MAX))
+            .build();
+        assert!(span.trace_id_high < 0);
+        assert_eq!(span.trace_id(), trace_id);
+        assert_eq!(span.span_id, -1);
+        assert_eq!(span.id(), SpanId(u64::MAX));
+    }
+}
@@ -0,0 +1,442 @@
+//! Serde support for the generated Jaeger types, following the JSON model used by
+//! Jaeger's query API and UI.
+//!
+//! The generated types are left untouched; each is converted to and from a mirror
+//! struct carrying the JSON field names. Ids are written as hexadecimal strings,
+//! enums by name, and binary tag values as base64.
+
+use serde::de::{self, Deserializer};
+use serde::ser::{self, Serializer};
+use serde::{Deserialize, Serialize};
+use thrift::OrderedFloat;
+
+use super::{
+    Batch, Log, OwnedTagValue, Process, Span, SpanId, SpanRef, SpanRefType, Tag, TagType, TagValue,
+    TraceId,
+};
+
+impl Serialize for TraceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for TraceId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        TraceId::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for SpanId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        SpanId::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
+const TAG_TYPE_NAMES: &[(TagType, &str)] = &[
+    (TagType::STRING, "string"),
+    (TagType::BOOL, "bool"),
+    (TagType::LONG, "int64"),
+    (TagType::DOUBLE, "float64"),
+    (TagType::BINARY, "binary"),
+];
+
+const SPAN_REF_TYPE_NAMES: &[(SpanRefType, &str)] = &[
+    (SpanRefType::CHILD_OF, "CHILD_OF"),
+    (SpanRefType::FOLLOWS_FROM, "FOLLOWS_FROM"),
+];
+
+impl Serialize for TagType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (_, name) = TAG_TYPE_NAMES
+            .iter()
+            .find(|(tag_type, _)| tag_type == self)
+            .ok_or_else(|| ser::Error::custom(format!("Unknown tag type: {}", self.0)))?;
+        serializer.serialize_str(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for TagType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        TAG_TYPE_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(&name))
+            .map(|(tag_type, _)| *tag_type)
+            .ok_or_else(|| de::Error::custom(format!("Unknown tag type: {}", name)))
+    }
+}
+
+impl Serialize for SpanRefType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (_, name) = SPAN_REF_TYPE_NAMES
+            .iter()
+            .find(|(ref_type, _)| ref_type == self)
+            .ok_or_else(|| ser::Error::custom(format!("Unknown reference type: {}", self.0)))?;
+        serializer.serialize_str(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanRefType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        SPAN_REF_TYPE_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(&name))
+            .map(|(ref_type, _)| *ref_type)
+            .ok_or_else(|| de::Error::custom(format!("Unknown reference type: {}", name)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TagJson {
+    key: String,
+    #[serde(rename = "type")]
+    v_type: TagType,
+    value: OwnedTagValue,
+}
+
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = match self.value().map_err(ser::Error::custom)? {
+            TagValue::String(value) => OwnedTagValue::String(value.to_owned()),
+            TagValue::Double(value) => OwnedTagValue::Double(value),
+            TagValue::Bool(value) => OwnedTagValue::Bool(value),
+            TagValue::Long(value) => OwnedTagValue::Long(value),
+            TagValue::Binary(value) => OwnedTagValue::String(base64::encode(value)),
+        };
+        TagJson {
+            key: self.key.clone(),
+            v_type: self.v_type,
+            value,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    /// The value is interpreted according to the tag's `type`, so that, for
+    /// example, a `float64` tag written as `1` is still read as a double.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let TagJson { key, v_type, value } = TagJson::deserialize(deserializer)?;
+        let mismatch = |value: &OwnedTagValue| {
+            de::Error::custom(format!(
+                "Value {:?} of tag {} does not match its type",
+                value, key
+            ))
+        };
+        let mut tag = Tag::new(key.clone(), v_type, None, None, None, None, None);
+        match (v_type, value) {
+            (TagType::STRING, OwnedTagValue::String(value)) => tag.v_str = Some(value),
+            (TagType::BOOL, OwnedTagValue::Bool(value)) => tag.v_bool = Some(value),
+            (TagType::LONG, OwnedTagValue::Long(value)) => tag.v_long = Some(value),
+            (TagType::DOUBLE, OwnedTagValue::Double(value)) => {
+                tag.v_double = Some(OrderedFloat::from(value))
+            }
+            (TagType::DOUBLE, OwnedTagValue::Long(value)) => {
+                tag.v_double = Some(OrderedFloat::from(value as f64))
+            }
+            (TagType::BINARY, OwnedTagValue::String(value)) => {
+                tag.v_binary = Some(base64::decode(&value).map_err(de::Error::custom)?)
+            }
+            (TagType::BINARY, OwnedTagValue::Binary(value)) => tag.v_binary = Some(value),
+            (_, value) => return Err(mismatch(&value)),
+        }
+        Ok(tag)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogJson {
+    timestamp: i64,
+    fields: Vec<Tag>,
+}
+
+impl Serialize for Log {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LogJson {
+            timestamp: self.timestamp,
+            fields: self.fields.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Log {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let LogJson { timestamp, fields } = LogJson::deserialize(deserializer)?;
+        Ok(Log::new(timestamp, fields))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpanRefJson {
+    #[serde(rename = "refType")]
+    ref_type: SpanRefType,
+    #[serde(rename = "traceID")]
+    trace_id: TraceId,
+    #[serde(rename = "spanID")]
+    span_id: SpanId,
+}
+
+impl Serialize for SpanRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SpanRefJson {
+            ref_type: self.ref_type,
+            trace_id: self.trace_id(),
+            span_id: self.referenced_span_id(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SpanRefJson {
+            ref_type,
+            trace_id,
+            span_id,
+        } = SpanRefJson::deserialize(deserializer)?;
+        Ok(SpanRef::new(
+            ref_type,
+            trace_id.jaeger_low(),
+            trace_id.jaeger_high(),
+            span_id.to_jaeger(),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpanJson {
+    #[serde(rename = "traceID")]
+    trace_id: TraceId,
+    #[serde(rename = "spanID")]
+    span_id: SpanId,
+    #[serde(default)]
+    flags: i32,
+    operation_name: String,
+    #[serde(default)]
+    references: Vec<SpanRef>,
+    start_time: i64,
+    duration: i64,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    logs: Vec<Log>,
+}
+
+impl Serialize for Span {
+    /// Jaeger's JSON model has no parent span id; as in Jaeger, the parent is
+    /// written as a `CHILD_OF` reference unless one is already present.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut references = self.references.clone().unwrap_or_default();
+        let has_parent_reference = references.iter().any(|r| {
+            r.ref_type == SpanRefType::CHILD_OF
+                && r.is_within_trace_of(self)
+                && r.span_id == self.parent_span_id
+        });
+        if self.parent_span_id != 0 && !has_parent_reference {
+            references.insert(
+                0,
+                SpanRef::new(
+                    SpanRefType::CHILD_OF,
+                    self.trace_id_low,
+                    self.trace_id_high,
+                    self.parent_span_id,
+                ),
+            );
+        }
+
+        SpanJson {
+            trace_id: self.trace_id(),
+            span_id: self.id(),
+            flags: self.flags,
+            operation_name: self.operation_name.clone(),
+            references,
+            start_time: self.start_time,
+            duration: self.duration,
+            tags: self.tags.clone().unwrap_or_default(),
+            logs: self.logs.clone().unwrap_or_default(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The first `CHILD_OF` reference within the same trace is read back as the
+/// span's parent and removed from the references, reversing [`Serialize`].
+///
+/// Spans which record their parent only in `parent_span_id`, as the OpenTelemetry
+/// Jaeger exporter and [`SpanBuilder`](crate::jaeger_models::SpanBuilder) do,
+/// therefore survive a round trip unchanged. The conversion is lossy for other
+/// spans: a `CHILD_OF` reference is folded into `parent_span_id` even if the
+/// original span recorded its parent only as a reference, or in both places, and
+/// empty lists of references, tags or logs are read back as absent.
+impl<'de> Deserialize<'de> for Span {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = SpanJson::deserialize(deserializer)?;
+        let mut references = json.references;
+        let parent_reference = references
+            .iter()
+            .position(|r| r.ref_type == SpanRefType::CHILD_OF && r.trace_id() == json.trace_id);
+        let parent_span_id = match parent_reference {
+            Some(position) => references.remove(position).span_id,
+            None => 0,
+        };
+
+        Ok(Span::new(
+            json.trace_id.jaeger_low(),
+            json.trace_id.jaeger_high(),
+            json.span_id.to_jaeger(),
+            parent_span_id,
+            json.operation_name,
+            Some(references).filter(|r| !r.is_empty()),
+            json.flags,
+            json.start_time,
+            json.duration,
+            Some(json.tags).filter(|t| !t.is_empty()),
+            Some(json.logs).filter(|l| !l.is_empty()),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessJson {
+    service_name: String,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+impl Serialize for Process {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProcessJson {
+            service_name: self.service_name.clone(),
+            tags: self.tags.clone().unwrap_or_default(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Process {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = ProcessJson::deserialize(deserializer)?;
+        Ok(Process::new(
+            json.service_name,
+            Some(json.tags).filter(|t| !t.is_empty()),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BatchJson {
+    process: Process,
+    spans: Vec<Span>,
+}
+
+impl Serialize for Batch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BatchJson {
+            process: self.process.clone(),
+            spans: self.spans.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Batch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let BatchJson { process, spans } = BatchJson::deserialize(deserializer)?;
+        Ok(Batch::new(process, spans, None, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::jaeger_models::{BatchBuilder, SpanBuilder};
+
+    #[test]
+    fn batches_round_trip() {
+        let root = SpanBuilder::new("POST /items")
+            .tag("http.status_code", 200i64)
+            .tag("ratio", 0.5)
+            .tag("cached", true)
+            .tag("payload", vec![1u8, 2, 3])
+            .event(1_000, "started")
+            .build();
+        let child = SpanBuilder::new("GET /stock")
+            .child_of(&root)
+            .follows_from(&root)
+            .build();
+        let batch = BatchBuilder::new("cart_server")
+            .process_tag("hostname", "localhost")
+            .span(root)
+            .span(child)
+            .build();
+
+        let json = serde_json::to_string(&batch).unwrap();
+        let round_tripped: Batch = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            round_tripped,
+            Batch::new(batch.process, batch.spans, None, None)
+        );
+    }
+
+    #[test]
+    fn spans_use_jaeger_field_names_and_write_the_parent_as_a_reference() {
+        let parent = SpanBuilder::new("parent").trace_id(TraceId(1)).build();
+        let child = SpanBuilder::new("child")
+            .child_of(&parent)
+            .tag("count", 3i64)
+            .build();
+
+        let json = serde_json::to_value(&child).unwrap();
+
+        assert_eq!(json["traceID"], "00000000000000000000000000000001");
+        assert_eq!(json["operationName"], "child");
+        assert_eq!(
+            json["references"],
+            json!([{
+                "refType": "CHILD_OF",
+                "traceID": "00000000000000000000000000000001",
+                "spanID": parent.id().to_string(),
+            }])
+        );
+        assert_eq!(
+            json["tags"],
+            json!([{ "key": "count", "type": "int64", "value": 3 }])
+        );
+    }
+
+    #[test]
+    fn child_of_references_are_read_back_as_the_parent() {
+        let parent = SpanBuilder::new("parent").build();
+        let mut child = SpanBuilder::new("child")
+            .trace_id(parent.trace_id())
+            .build();
+        child.references = Some(vec![SpanRef::new(
+            SpanRefType::CHILD_OF,
+            parent.trace_id_low,
+            parent.trace_id_high,
+            parent.span_id,
+        )]);
+
+        let json = serde_json::to_string(&child).unwrap();
+        let round_tripped: Span = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(round_tripped.parent_span_id, parent.span_id);
+        assert_eq!(round_tripped.references, None);
+    }
+}
@@ -1,6 +1,22 @@
+mod analysis;
+mod assertions;
+mod builders;
+mod encoding;
 mod extensions;
 mod generated;
-pub(crate) mod span_tree;
+mod ids;
+mod json;
+mod otel;
+mod render;
+mod trace;
 
+pub use analysis::*;
+pub use assertions::*;
+pub use builders::*;
+pub use encoding::*;
 pub use extensions::*;
 pub use generated::*;
+pub use ids::*;
+pub use otel::*;
+pub use render::*;
+pub use trace::*;
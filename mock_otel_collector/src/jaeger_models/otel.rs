@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::{SpanKind, StatusCode};
+use opentelemetry::{Key, Value};
+
+use super::{
+    Log, OwnedTagValue, Process, Span, SpanId, SpanRef, SpanRefType, SpanStatusCode, Tag, Trace,
+    TraceId,
+};
+
+/// Tags used by Jaeger to encode properties which OpenTelemetry models
+/// explicitly, rather than as attributes.
+const SPAN_KIND_TAG: &str = "span.kind";
+const STATUS_CODE_TAG: &str = "otel.status_code";
+const STATUS_DESCRIPTION_TAG: &str = "otel.status_description";
+const ERROR_TAG: &str = "error";
+const EVENT_NAME_FIELD: &str = "event";
+const SERVICE_NAME_ATTRIBUTE: &str = "service.name";
+
+/// A span in the shape of OpenTelemetry's [`SpanData`], which can be built from
+/// either a Jaeger [`Span`] received by the mock collector or a `SpanData`
+/// captured by an in-process exporter, so that both can be asserted on with
+/// the same code.
+///
+/// Conversion to and from Jaeger is two-way, but conversion from `SpanData` is
+/// one-way: `SpanData` is produced by the SDK's span processors rather than by
+/// tests, so spans from both sources are compared as `OtelSpan`s instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OtelSpan {
+    pub trace_id: TraceId,
+    pub span_id: SpanId,
+    pub parent_span_id: Option<SpanId>,
+    pub span_kind: SpanKind,
+    pub name: String,
+    pub start_time: SystemTime,
+    pub end_time: SystemTime,
+    pub attributes: BTreeMap<String, OwnedTagValue>,
+    pub events: Vec<OtelEvent>,
+    pub links: Vec<OtelLink>,
+    pub status_code: StatusCode,
+    pub status_message: String,
+    pub resource: BTreeMap<String, OwnedTagValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OtelEvent {
+    pub name: String,
+    pub timestamp: SystemTime,
+    pub attributes: BTreeMap<String, OwnedTagValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OtelLink {
+    pub trace_id: TraceId,
+    pub span_id: SpanId,
+    pub attributes: BTreeMap<String, OwnedTagValue>,
+}
+
+impl OtelSpan {
+    /// Convert a Jaeger span, and the process which reported it, into an [`OtelSpan`].
+    ///
+    /// The span kind and status are read from the tags Jaeger uses to encode them,
+    /// and those tags are not repeated amongst the attributes. Logs become events,
+    /// and `FOLLOWS_FROM` references, or references to other traces, become links.
+    pub fn from_jaeger(span: &Span, process: &Process) -> Self {
+        let status = span.status();
+        let parent_span_id = span.parent_id();
+
+        let attributes = span
+            .tags
+            .iter()
+            .flatten()
+            .filter(|tag| {
+                ![
+                    SPAN_KIND_TAG,
+                    STATUS_CODE_TAG,
+                    STATUS_DESCRIPTION_TAG,
+                    ERROR_TAG,
+                ]
+                .contains(&tag.key.as_str())
+            })
+            .filter_map(|tag| Some((tag.key.clone(), tag.owned_value().ok()?)))
+            .collect();
+
+        let events = span
+            .events()
+            .map(|log| OtelEvent {
+                name: log.event_name().unwrap_or_default().to_owned(),
+                timestamp: log.time(),
+                attributes: log
+                    .fields
+                    .iter()
+                    .filter(|field| field.key != EVENT_NAME_FIELD)
+                    .filter_map(|field| Some((field.key.clone(), field.owned_value().ok()?)))
+                    .collect(),
+            })
+            .collect();
+
+        let links = span
+            .references
+            .iter()
+            .flatten()
+            .filter(|r| {
+                !(r.is_within_trace_of(span) && Some(r.referenced_span_id()) == parent_span_id)
+            })
+            .map(|r| OtelLink {
+                trace_id: r.trace_id(),
+                span_id: r.referenced_span_id(),
+                attributes: BTreeMap::new(),
+            })
+            .collect();
+
+        let mut resource: BTreeMap<String, OwnedTagValue> = process
+            .tags
+            .iter()
+            .flatten()
+            .filter_map(|tag| Some((tag.key.clone(), tag.owned_value().ok()?)))
+            .collect();
+        resource.insert(
+            SERVICE_NAME_ATTRIBUTE.to_owned(),
+            process.service_name.clone().into(),
+        );
+
+        OtelSpan {
+            trace_id: span.trace_id(),
+            span_id: span.id(),
+            parent_span_id,
+            span_kind: match span.span_kind() {
+                Some("client") => SpanKind::Client,
+                Some("server") => SpanKind::Server,
+                Some("producer") => SpanKind::Producer,
+                Some("consumer") => SpanKind::Consumer,
+                _ => SpanKind::Internal,
+            },
+            name: span.operation_name.clone(),
+            start_time: micros_to_time(span.start_time),
+            end_time: micros_to_time(span.start_time + span.duration),
+            attributes,
+            events,
+            links,
+            status_code: match status.code {
+                SpanStatusCode::Unset => StatusCode::Unset,
+                SpanStatusCode::Ok => StatusCode::Ok,
+                SpanStatusCode::Error => StatusCode::Error,
+            },
+            status_message: status.description.unwrap_or_default(),
+            resource,
+        }
+    }
+
+    /// Convert this span back into a Jaeger span and the process which reported it,
+    /// encoding the span kind, status, events and links as the OpenTelemetry Jaeger
+    /// exporter does.
+    pub fn to_jaeger(&self) -> (Span, Process) {
+        let mut tags: Vec<Tag> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| Tag::from_value(key.as_str(), value.clone()))
+            .collect();
+
+        let span_kind = match self.span_kind {
+            SpanKind::Client => Some("client"),
+            SpanKind::Server => Some("server"),
+            SpanKind::Producer => Some("producer"),
+            SpanKind::Consumer => Some("consumer"),
+            SpanKind::Internal => None,
+        };
+        if let Some(span_kind) = span_kind {
+            tags.push(Tag::from_value(SPAN_KIND_TAG, span_kind));
+        }
+
+        match self.status_code {
+            StatusCode::Unset => {}
+            StatusCode::Ok => tags.push(Tag::from_value(STATUS_CODE_TAG, "OK")),
+            StatusCode::Error => {
+                tags.push(Tag::from_value(STATUS_CODE_TAG, "ERROR"));
+                tags.push(Tag::from_value(ERROR_TAG, true));
+            }
+        }
+        if !self.status_message.is_empty() {
+            tags.push(Tag::from_value(
+                STATUS_DESCRIPTION_TAG,
+                self.status_message.as_str(),
+            ));
+        }
+
+        let logs: Vec<Log> = self
+            .events
+            .iter()
+            .map(|event| {
+                let mut fields: Vec<Tag> = event
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| Tag::from_value(key.as_str(), value.clone()))
+                    .collect();
+                fields.push(Tag::from_value(EVENT_NAME_FIELD, event.name.as_str()));
+                Log::new(time_to_micros(event.timestamp), fields)
+            })
+            .collect();
+
+        let references: Vec<SpanRef> = self
+            .links
+            .iter()
+            .map(|link| {
+                SpanRef::new(
+                    SpanRefType::FOLLOWS_FROM,
+                    link.trace_id.jaeger_low(),
+                    link.trace_id.jaeger_high(),
+                    link.span_id.to_jaeger(),
+                )
+            })
+            .collect();
+
+        let start_time = time_to_micros(self.start_time);
+        let span = Span::new(
+            self.trace_id.jaeger_low(),
+            self.trace_id.jaeger_high(),
+            self.span_id.to_jaeger(),
+            self.parent_span_id.map_or(0, |id| id.to_jaeger()),
+            self.name.clone(),
+            Some(references).filter(|r| !r.is_empty()),
+            1,
+            start_time,
+            time_to_micros(self.end_time) - start_time,
+            Some(tags).filter(|t| !t.is_empty()),
+            Some(logs).filter(|l| !l.is_empty()),
+        );
+
+        let service_name = match self.resource.get(SERVICE_NAME_ATTRIBUTE) {
+            Some(OwnedTagValue::String(service_name)) => service_name.clone(),
+            _ => "unknown_service".to_owned(),
+        };
+        let process_tags: Vec<Tag> = self
+            .resource
+            .iter()
+            .filter(|(key, _)| key.as_str() != SERVICE_NAME_ATTRIBUTE)
+            .map(|(key, value)| Tag::from_value(key.as_str(), value.clone()))
+            .collect();
+        let process = Process::new(service_name, Some(process_tags).filter(|t| !t.is_empty()));
+
+        (span, process)
+    }
+}
+
+impl From<&SpanData> for OtelSpan {
+    fn from(span_data: &SpanData) -> Self {
+        let parent_span_id = SpanId::from(span_data.parent_span_id);
+        OtelSpan {
+            trace_id: span_data.span_context.trace_id().into(),
+            span_id: span_data.span_context.span_id().into(),
+            parent_span_id: Some(parent_span_id).filter(|id| id.0 != 0),
+            span_kind: span_data.span_kind.clone(),
+            name: span_data.name.to_string(),
+            start_time: span_data.start_time,
+            end_time: span_data.end_time,
+            attributes: attributes_to_map(span_data.attributes.iter()),
+            events: span_data
+                .events
+                .iter()
+                .map(|event| OtelEvent {
+                    name: event.name.to_string(),
+                    timestamp: event.timestamp,
+                    attributes: attributes_to_map(
+                        event.attributes.iter().map(|kv| (&kv.key, &kv.value)),
+                    ),
+                })
+                .collect(),
+            links: span_data
+                .links
+                .iter()
+                .map(|link| OtelLink {
+                    trace_id: link.span_context().trace_id().into(),
+                    span_id: link.span_context().span_id().into(),
+                    attributes: attributes_to_map(
+                        link.attributes().iter().map(|kv| (&kv.key, &kv.value)),
+                    ),
+                })
+                .collect(),
+            status_code: span_data.status_code,
+            status_message: span_data.status_message.to_string(),
+            resource: resource_to_map(span_data.resource.clone().into()),
+        }
+    }
+}
+
+impl From<&Value> for OwnedTagValue {
+    /// Arrays have no counterpart amongst Jaeger's tag types, so are recorded as
+    /// strings, as the OpenTelemetry Jaeger exporter does.
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Bool(value) => OwnedTagValue::Bool(*value),
+            Value::I64(value) => OwnedTagValue::Long(*value),
+            Value::F64(value) => OwnedTagValue::Double(*value),
+            Value::String(value) => OwnedTagValue::String(value.to_string()),
+            Value::Array(_) => OwnedTagValue::String(value.as_str().into_owned()),
+        }
+    }
+}
+
+impl Trace {
+    /// Convert every span in this trace into an [`OtelSpan`], depth-first.
+    pub fn to_otel_spans(&self) -> Vec<OtelSpan> {
+        self.descendants()
+            .map(|span| {
+                let process = span
+                    .process()
+                    .cloned()
+                    .unwrap_or_else(|| Process::new("unknown_service".to_owned(), None));
+                OtelSpan::from_jaeger(&span, &process)
+            })
+            .collect()
+    }
+}
+
+fn attributes_to_map<'a>(
+    attributes: impl Iterator<Item = (&'a Key, &'a Value)>,
+) -> BTreeMap<String, OwnedTagValue> {
+    attributes
+        .map(|(key, value)| (key.as_str().to_owned(), value.into()))
+        .collect()
+}
+
+/// Newer SDKs only record a resource on a span if one was configured, so the
+/// resource is accepted whether or not it is optional.
+fn resource_to_map(resource: Option<Arc<Resource>>) -> BTreeMap<String, OwnedTagValue> {
+    resource.map_or_else(BTreeMap::new, |resource| attributes_to_map(resource.iter()))
+}
+
+fn micros_to_time(micros: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(micros.max(0) as u64)
+}
+
+fn time_to_micros(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_micros() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    const START: i64 = 1_000_000;
+
+    #[test]
+    fn jaeger_spans_round_trip_through_otel_spans() {
+        let parent = SpanBuilder::new("POST /checkout").start_time(START).build();
+        let other_trace = SpanBuilder::new("enqueue").build();
+        // Tags and fields are laid out in the order `to_jaeger` writes them, so the
+        // round-tripped span can be compared directly.
+        let span = SpanBuilder::new("reserve stock")
+            .child_of(&parent)
+            .follows_from(&other_trace)
+            .start_time(START + 10)
+            .duration(Duration::from_micros(50))
+            .tag("http.method", "POST")
+            .tag("http.status_code", 500i64)
+            .kind("server")
+            .error("out of stock")
+            .log(START + 20, vec![("event", "reserving")])
+            .log(
+                START + 30,
+                vec![
+                    ("exception.message", OwnedTagValue::from("out of stock")),
+                    ("exception.type", "StockError".into()),
+                    ("event", "exception".into()),
+                ],
+            )
+            .build();
+        let process = Process::new(
+            "cart".to_owned(),
+            Some(vec![Tag::from_value("host.name", "box")]),
+        );
+
+        let otel_span = OtelSpan::from_jaeger(&span, &process);
+        assert_eq!(otel_span.trace_id, parent.trace_id());
+        assert_eq!(otel_span.parent_span_id, Some(parent.id()));
+        assert_eq!(otel_span.span_kind, SpanKind::Server);
+        assert_eq!(otel_span.status_code, StatusCode::Error);
+        assert_eq!(otel_span.status_message, "out of stock");
+        assert_eq!(
+            otel_span.attributes.keys().collect::<Vec<_>>(),
+            vec!["http.method", "http.status_code"]
+        );
+        assert_eq!(
+            otel_span
+                .end_time
+                .duration_since(otel_span.start_time)
+                .unwrap(),
+            Duration::from_micros(50)
+        );
+        assert_eq!(
+            otel_span.events,
+            vec![
+                OtelEvent {
+                    name: "reserving".to_owned(),
+                    timestamp: micros_to_time(START + 20),
+                    attributes: BTreeMap::new(),
+                },
+                OtelEvent {
+                    name: "exception".to_owned(),
+                    timestamp: micros_to_time(START + 30),
+                    attributes: vec![
+                        ("exception.message".to_owned(), "out of stock".into()),
+                        ("exception.type".to_owned(), "StockError".into()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                },
+            ]
+        );
+        assert_eq!(
+            otel_span.links,
+            vec![OtelLink {
+                trace_id: other_trace.trace_id(),
+                span_id: other_trace.id(),
+                attributes: BTreeMap::new(),
+            }]
+        );
+        assert_eq!(otel_span.resource.len(), 2);
+        assert_eq!(
+            otel_span.resource["service.name"],
+            OwnedTagValue::from("cart")
+        );
+
+        assert_eq!(otel_span.to_jaeger(), (span, process));
+    }
+
+    #[test]
+    fn unset_status_and_internal_kind_add_no_tags() {
+        let span = SpanBuilder::new("load cart")
+            .start_time(START)
+            .tag("cart.id", 7i64)
+            .build();
+        let process = Process::new("cart".to_owned(), None);
+
+        let otel_span = OtelSpan::from_jaeger(&span, &process);
+        assert_eq!(otel_span.span_kind, SpanKind::Internal);
+        assert_eq!(otel_span.status_code, StatusCode::Unset);
+        assert_eq!(otel_span.status_message, "");
+        assert_eq!(otel_span.parent_span_id, None);
+        assert!(otel_span.links.is_empty());
+
+        assert_eq!(otel_span.to_jaeger(), (span, process));
+    }
+
+    #[test]
+    fn ok_status_round_trips_without_an_error_tag() {
+        let span = SpanBuilder::new("load cart")
+            .start_time(START)
+            .kind("client")
+            .tag("otel.status_code", "OK")
+            .build();
+        let process = Process::new("cart".to_owned(), None);
+
+        let otel_span = OtelSpan::from_jaeger(&span, &process);
+        assert_eq!(otel_span.span_kind, SpanKind::Client);
+        assert_eq!(otel_span.status_code, StatusCode::Ok);
+
+        assert_eq!(otel_span.to_jaeger(), (span, process));
+    }
+}
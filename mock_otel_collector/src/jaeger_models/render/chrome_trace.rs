@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::jaeger_models::{Tag, TagValue, Trace, TraceSpan};
+
+/// The placeholder for spans whose process is unknown.
+const UNKNOWN_SERVICE: &str = "unknown service";
+
+/// A thread within a service, identified by the `thread.id` and `thread.name`
+/// tags recorded on its spans.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ThreadKey {
+    id: Option<i64>,
+    name: Option<String>,
+}
+
+/// The spans on one track. Each track's spans are kept properly nested, as the
+/// trace viewers require, by recording the end times of the spans enclosing the
+/// most recently placed one.
+struct Lane {
+    open_ends: Vec<i64>,
+}
+
+impl Lane {
+    /// Place a span on this lane, if it nests within or follows the spans
+    /// already placed.
+    fn try_place(&mut self, start: i64, end: i64) -> bool {
+        while self.open_ends.last().map_or(false, |&open| open <= start) {
+            self.open_ends.pop();
+        }
+        if self.open_ends.last().map_or(true, |&open| end <= open) {
+            self.open_ends.push(end);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Trace {
+    /// Convert this trace to the Chrome trace event format, which can be opened
+    /// in `ui.perfetto.dev` or `chrome://tracing`.
+    ///
+    /// Each service is shown as a process, with a track for each thread its
+    /// spans were recorded on. Concurrent spans on the same thread, such as
+    /// those of interleaved async tasks, rarely nest within each other, so any
+    /// span which would overlap another on its thread's track is moved to an
+    /// additional track for that thread. Span events are shown as instants.
+    /// Times are relative to the start of the root span.
+    pub fn to_chrome_trace(&self) -> Value {
+        let trace_start = self.root().start_time;
+
+        let mut spans: Vec<TraceSpan> = self.descendants().collect();
+        // Enclosing spans must be placed before the spans they enclose.
+        spans.sort_by_key(|span| (span.start_time, -span.duration.max(0)));
+
+        let mut services: BTreeMap<&str, BTreeMap<ThreadKey, Vec<Lane>>> = BTreeMap::new();
+        let mut placements = Vec::new();
+        for span in &spans {
+            let service = span.service_name().unwrap_or(UNKNOWN_SERVICE);
+            let thread = thread_key(*span);
+            let lanes = services
+                .entry(service)
+                .or_default()
+                .entry(thread.clone())
+                .or_default();
+
+            let start = span.start_time;
+            let end = start + span.duration.max(0);
+            let lane = match lanes.iter_mut().position(|lane| lane.try_place(start, end)) {
+                Some(lane) => lane,
+                None => {
+                    lanes.push(Lane {
+                        open_ends: vec![end],
+                    });
+                    lanes.len() - 1
+                }
+            };
+            placements.push((service, thread, lane));
+        }
+
+        // Number processes and threads in name order, so that the output is
+        // stable, with each thread's additional tracks following it.
+        let mut pids = BTreeMap::new();
+        let mut tids = BTreeMap::new();
+        let mut events = Vec::new();
+        for (pid, (service, threads)) in services.iter().enumerate() {
+            let pid = pid + 1;
+            pids.insert(*service, pid);
+            events.push(json!({
+                "name": "process_name",
+                "ph": "M",
+                "pid": pid,
+                "args": { "name": service },
+            }));
+
+            let mut tid = 0;
+            for (thread, lanes) in threads {
+                for lane in 0..lanes.len() {
+                    tid += 1;
+                    tids.insert((*service, thread.clone(), lane), tid);
+                    events.push(json!({
+                        "name": "thread_name",
+                        "ph": "M",
+                        "pid": pid,
+                        "tid": tid,
+                        "args": { "name": thread_name(thread, lane) },
+                    }));
+                }
+            }
+        }
+
+        for (span, (service, thread, lane)) in spans.iter().zip(placements) {
+            let pid = pids[service];
+            let tid = tids[&(service, thread, lane)];
+
+            let mut args = Map::new();
+            args.insert("span_id".to_owned(), json!(span.id().to_string()));
+            if let Some(parent) = span.parent() {
+                args.insert("parent_span_id".to_owned(), json!(parent.id().to_string()));
+            }
+            for tag in span.tags.iter().flatten() {
+                args.insert(tag.key.clone(), tag_json(tag));
+            }
+            events.push(json!({
+                "name": span.operation_name,
+                "cat": service,
+                "ph": "X",
+                "ts": span.start_time - trace_start,
+                "dur": span.duration.max(0),
+                "pid": pid,
+                "tid": tid,
+                "args": args,
+            }));
+
+            for event in span.events() {
+                let args: Map<String, Value> = event
+                    .fields
+                    .iter()
+                    .map(|field| (field.key.clone(), tag_json(field)))
+                    .collect();
+                events.push(json!({
+                    "name": event.event_name().unwrap_or("event"),
+                    "cat": service,
+                    "ph": "i",
+                    "s": "t",
+                    "ts": event.timestamp - trace_start,
+                    "pid": pid,
+                    "tid": tid,
+                    "args": args,
+                }));
+            }
+        }
+
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+            "otherData": { "trace_id": self.trace_id().to_string() },
+        })
+    }
+}
+
+fn thread_key(span: TraceSpan) -> ThreadKey {
+    let value = |key| span.get_tag(key).and_then(|tag| tag.value().ok());
+    ThreadKey {
+        id: match value("thread.id") {
+            Some(TagValue::Long(id)) => Some(id),
+            Some(TagValue::String(id)) => id.parse().ok(),
+            _ => None,
+        },
+        name: match value("thread.name") {
+            Some(TagValue::String(name)) => Some(name.to_owned()),
+            _ => None,
+        },
+    }
+}
+
+fn thread_name(thread: &ThreadKey, lane: usize) -> String {
+    let name = match (&thread.name, thread.id) {
+        (Some(name), Some(id)) => format!("{} ({})", name, id),
+        (Some(name), None) => name.clone(),
+        (None, Some(id)) => format!("thread {}", id),
+        (None, None) => "unknown thread".to_owned(),
+    };
+    if lane == 0 {
+        name
+    } else {
+        format!("{} [overlapping {}]", name, lane)
+    }
+}
+
+fn tag_json(tag: &Tag) -> Value {
+    match tag.value() {
+        Ok(TagValue::String(value)) => json!(value),
+        Ok(TagValue::Double(value)) => json!(value),
+        Ok(TagValue::Bool(value)) => json!(value),
+        Ok(TagValue::Long(value)) => json!(value),
+        Ok(value @ TagValue::Binary(_)) => json!(value.to_string()),
+        Err(_) => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use crate::jaeger_models::{Batch, BatchBuilder, SpanBuilder, Trace};
+
+    fn assemble(batches: Vec<Batch>) -> Trace {
+        Trace::from_process_spans(batches.into_iter().flat_map(|batch| {
+            let process = batch.process;
+            batch
+                .spans
+                .into_iter()
+                .map(move |span| (span, Some(process.clone())))
+        }))
+        .unwrap()
+    }
+
+    fn events<'v>(chrome_trace: &'v Value, phase: &str) -> Vec<&'v Value> {
+        chrome_trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|event| event["ph"] == phase)
+            .collect()
+    }
+
+    #[test]
+    fn services_become_processes_and_threads_become_tracks() {
+        let root = SpanBuilder::new("POST /items")
+            .start_time(1_000)
+            .duration(Duration::from_micros(100))
+            .tag("thread.id", 2i64)
+            .tag("thread.name", "worker")
+            .event(1_050, "cache miss")
+            .build();
+        let call = SpanBuilder::new("GET /stock")
+            .child_of(&root)
+            .start_offset(Duration::from_micros(10))
+            .duration(Duration::from_micros(20))
+            .build();
+        let trace = assemble(vec![
+            BatchBuilder::new("stock_service").span(call).build(),
+            BatchBuilder::new("cart_server").span(root).build(),
+        ]);
+
+        let chrome_trace = trace.to_chrome_trace();
+
+        let names: Vec<(&Value, &Value, &Value)> = events(&chrome_trace, "M")
+            .iter()
+            .map(|event| (&event["name"], &event["pid"], &event["args"]["name"]))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (&json!("process_name"), &json!(1), &json!("cart_server")),
+                (&json!("thread_name"), &json!(1), &json!("worker (2)")),
+                (&json!("process_name"), &json!(2), &json!("stock_service")),
+                (&json!("thread_name"), &json!(2), &json!("unknown thread")),
+            ]
+        );
+
+        let spans = events(&chrome_trace, "X");
+        assert_eq!(spans[0]["name"], "POST /items");
+        assert_eq!(
+            (&spans[0]["ts"], &spans[0]["dur"]),
+            (&json!(0), &json!(100))
+        );
+        assert_eq!(spans[0]["args"]["thread.name"], "worker");
+        assert_eq!(spans[1]["name"], "GET /stock");
+        assert_eq!((&spans[1]["pid"], &spans[1]["ts"]), (&json!(2), &json!(10)));
+        assert_eq!(
+            spans[1]["args"]["parent_span_id"],
+            spans[0]["args"]["span_id"]
+        );
+
+        let instants = events(&chrome_trace, "i");
+        assert_eq!(instants.len(), 1);
+        assert_eq!(instants[0]["name"], "cache miss");
+        assert_eq!(
+            (&instants[0]["ts"], &instants[0]["tid"]),
+            (&json!(50), &json!(1))
+        );
+        assert_eq!(
+            chrome_trace["otherData"]["trace_id"],
+            trace.trace_id().to_string()
+        );
+    }
+
+    #[test]
+    fn overlapping_spans_are_moved_to_additional_tracks() {
+        let root = SpanBuilder::new("root")
+            .start_time(0)
+            .duration(Duration::from_micros(100))
+            .build();
+        let child = |start, duration| {
+            SpanBuilder::new("task")
+                .child_of(&root)
+                .start_time(start)
+                .duration(Duration::from_micros(duration))
+                .build()
+        };
+        let trace = assemble(vec![BatchBuilder::new("cart_server")
+            .spans(vec![
+                root.clone(),
+                child(10, 40),
+                child(20, 40),
+                child(60, 20),
+            ])
+            .build()]);
+
+        let chrome_trace = trace.to_chrome_trace();
+
+        let thread_names: Vec<&Value> = events(&chrome_trace, "M")
+            .iter()
+            .filter(|event| event["name"] == "thread_name")
+            .map(|event| &event["args"]["name"])
+            .collect();
+        assert_eq!(
+            thread_names,
+            [
+                &json!("unknown thread"),
+                &json!("unknown thread [overlapping 1]")
+            ]
+        );
+        let tracks: Vec<(&Value, &Value)> = events(&chrome_trace, "X")
+            .iter()
+            .map(|event| (&event["ts"], &event["tid"]))
+            .collect();
+        assert_eq!(
+            tracks,
+            [
+                (&json!(0), &json!(1)),
+                (&json!(10), &json!(1)),
+                (&json!(20), &json!(2)),
+                (&json!(60), &json!(1)),
+            ]
+        );
+    }
+}
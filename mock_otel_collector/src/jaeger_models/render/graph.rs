@@ -0,0 +1,219 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::format_duration;
+use crate::jaeger_models::{Trace, TraceSpan};
+
+/// A trace as a Graphviz DOT digraph, with a node per span labelled with its
+/// operation, service and duration. Child-of references are drawn as solid
+/// edges, follows-from references as dashed ones, and failed spans in red.
+///
+/// ```text
+/// digraph trace {
+///     node [shape=box];
+///     s0 [label="POST /items\n[cart_server]\n4.2ms"];
+///     s1 [label="GET /stock/1234\n[cart_server]\n3.1ms", color=red];
+///     s0 -> s1;
+/// }
+/// ```
+pub struct Dot<'t> {
+    trace: &'t Trace,
+}
+
+/// A trace as a Mermaid flowchart, drawn in the same way as [`Dot`].
+///
+/// ```text
+/// flowchart TD
+///     s0["POST /items<br/>[cart_server]<br/>4.2ms"]
+///     s1["GET /stock/1234<br/>[cart_server]<br/>3.1ms"]:::error
+///     s0 --> s1
+///     classDef error stroke:#d00,stroke-width:2px
+/// ```
+pub struct Mermaid<'t> {
+    trace: &'t Trace,
+}
+
+impl Trace {
+    /// Render this trace as a Graphviz [`Dot`] graph.
+    pub fn dot(&self) -> Dot<'_> {
+        Dot { trace: self }
+    }
+
+    /// Render this trace as a [`Mermaid`] flowchart.
+    pub fn mermaid(&self) -> Mermaid<'_> {
+        Mermaid { trace: self }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EdgeKind {
+    ChildOf,
+    FollowsFrom,
+}
+
+/// Every edge in the trace, from the referenced span to the referencing one.
+/// A span whose parent is only a follows-from reference gets a single
+/// follows-from edge to it, rather than an edge of each kind.
+fn edges(trace: &Trace) -> Vec<(TraceSpan<'_>, TraceSpan<'_>, EdgeKind)> {
+    let mut edges = Vec::new();
+    for span in trace.descendants() {
+        if let Some(parent) = span.parent() {
+            if !span
+                .follows_from_spans()
+                .any(|s| s.index() == parent.index())
+            {
+                edges.push((parent, span, EdgeKind::ChildOf));
+            }
+        }
+        for source in span.follows_from_spans() {
+            edges.push((source, span, EdgeKind::FollowsFrom));
+        }
+    }
+    edges
+}
+
+/// The lines of a node's label: operation, service (if known) and duration.
+fn label_lines(span: TraceSpan) -> Vec<String> {
+    let mut lines = vec![span.operation_name.clone()];
+    if let Some(service_name) = span.service_name() {
+        lines.push(format!("[{}]", service_name));
+    }
+    lines.push(format_duration(span.duration));
+    lines
+}
+
+impl<'t> Display for Dot<'t> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "digraph trace {{")?;
+        writeln!(f, "    node [shape=box];")?;
+        for span in self.trace.descendants() {
+            let label: Vec<String> = label_lines(span)
+                .iter()
+                .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+                .collect();
+            write!(f, "    s{} [label=\"{}\"", span.index(), label.join("\\n"))?;
+            if span.status().is_error() {
+                write!(f, ", color=red")?;
+            }
+            writeln!(f, "];")?;
+        }
+        for (source, target, kind) in edges(self.trace) {
+            match kind {
+                EdgeKind::ChildOf => {
+                    writeln!(f, "    s{} -> s{};", source.index(), target.index())?
+                }
+                EdgeKind::FollowsFrom => writeln!(
+                    f,
+                    "    s{} -> s{} [style=dashed, label=\"follows from\"];",
+                    source.index(),
+                    target.index()
+                )?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl<'t> Display for Mermaid<'t> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "flowchart TD")?;
+        let mut has_errors = false;
+        for span in self.trace.descendants() {
+            let label: Vec<String> = label_lines(span)
+                .iter()
+                .map(|line| {
+                    line.replace('&', "#amp;")
+                        .replace('"', "#quot;")
+                        .replace('<', "#lt;")
+                        .replace('>', "#gt;")
+                })
+                .collect();
+            write!(f, "    s{}[\"{}\"]", span.index(), label.join("<br/>"))?;
+            if span.status().is_error() {
+                has_errors = true;
+                write!(f, ":::error")?;
+            }
+            writeln!(f)?;
+        }
+        for (source, target, kind) in edges(self.trace) {
+            let arrow = match kind {
+                EdgeKind::ChildOf => "-->",
+                EdgeKind::FollowsFrom => "-.->|follows from|",
+            };
+            writeln!(f, "    s{} {} s{}", source.index(), arrow, target.index())?;
+        }
+        if has_errors {
+            writeln!(f, "    classDef error stroke:#d00,stroke-width:2px")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jaeger_models::{BatchBuilder, SpanBuilder, Trace};
+
+    /// A root span, a failed child whose name needs escaping, and a span which
+    /// follows from that child.
+    fn trace() -> Trace {
+        let root = SpanBuilder::new("POST /items").build();
+        let child = SpanBuilder::new(r#"GET "a\b" <c> & d"#)
+            .child_of(&root)
+            .error("unavailable")
+            .build();
+        let follower = SpanBuilder::new("publish")
+            .trace_id(root.trace_id())
+            .follows_from(&child)
+            .build();
+        let batch = BatchBuilder::new("cart_server")
+            .spans(vec![root, child, follower])
+            .build();
+        Trace::from_process_spans(
+            batch
+                .spans
+                .into_iter()
+                .map(|span| (span, Some(batch.process.clone()))),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dot_escapes_labels_and_styles_edges() {
+        assert_eq!(
+            trace().dot().to_string(),
+            r#"digraph trace {
+    node [shape=box];
+    s0 [label="POST /items\n[cart_server]\n1ms"];
+    s1 [label="GET \"a\\b\" <c> & d\n[cart_server]\n1ms", color=red];
+    s2 [label="publish\n[cart_server]\n1ms"];
+    s0 -> s1;
+    s1 -> s2 [style=dashed, label="follows from"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn mermaid_escapes_labels_and_styles_edges() {
+        assert_eq!(
+            trace().mermaid().to_string(),
+            r#"flowchart TD
+    s0["POST /items<br/>[cart_server]<br/>1ms"]
+    s1["GET #quot;a\b#quot; #lt;c#gt; #amp; d<br/>[cart_server]<br/>1ms"]:::error
+    s2["publish<br/>[cart_server]<br/>1ms"]
+    s0 --> s1
+    s1 -.->|follows from| s2
+    classDef error stroke:#d00,stroke-width:2px
+"#
+        );
+    }
+
+    #[test]
+    fn mermaid_only_defines_the_error_class_when_needed() {
+        let trace = Trace::from_spans(vec![SpanBuilder::new("root").build()]).unwrap();
+
+        assert_eq!(
+            trace.mermaid().to_string(),
+            "flowchart TD\n    s0[\"root<br/>1ms\"]\n"
+        );
+    }
+}
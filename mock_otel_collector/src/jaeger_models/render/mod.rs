@@ -0,0 +1,10 @@
+mod chrome_trace;
+mod graph;
+mod snapshot;
+mod tree;
+mod waterfall;
+
+pub use graph::*;
+pub use snapshot::*;
+pub(crate) use tree::format_duration;
+pub use waterfall::*;
@@ -0,0 +1,206 @@
+use std::fmt::Write;
+
+use regex::Regex;
+
+use crate::jaeger_models::{OwnedTagValue, SpanStatusCode, Trace, TraceSpan};
+
+/// Settings controlling how [`Trace::to_snapshot`] normalises a trace.
+#[derive(Clone, Debug)]
+pub struct SnapshotSettings {
+    /// Tags left out of the snapshot entirely.
+    pub omitted_tags: Vec<String>,
+
+    /// Tags whose presence is recorded, but whose value varies between runs and
+    /// so is replaced with `{redacted}`. Tags whose key ends in `.port` are
+    /// always redacted.
+    pub redacted_tags: Vec<String>,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            omitted_tags: Vec::new(),
+            redacted_tags: [
+                "busy_ns",
+                "idle_ns",
+                "thread.id",
+                "thread.name",
+                "code.lineno",
+                "net.peer.ip",
+                "http.client_ip",
+            ]
+            .iter()
+            .map(|key| key.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// Replaces values within strings which differ from one run to the next.
+struct Redactor {
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl Redactor {
+    fn new() -> Self {
+        let rule = |pattern: &str, replacement| (Regex::new(pattern).unwrap(), replacement);
+        Self {
+            rules: vec![
+                rule(
+                    r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+                    "{uuid}",
+                ),
+                rule(r"\b[0-9a-f]{32}\b", "{trace_id}"),
+                rule(r"\b[0-9a-f]{16}\b", "{span_id}"),
+                rule(r"\b((?:\d{1,3}\.){3}\d{1,3}|localhost):\d+", "$1:{port}"),
+            ],
+        }
+    }
+
+    fn redact(&self, value: &str) -> String {
+        self.rules
+            .iter()
+            .fold(value.to_owned(), |value, (pattern, replacement)| {
+                pattern.replace_all(&value, *replacement).into_owned()
+            })
+    }
+}
+
+impl Trace {
+    /// Render the trace as a tree suitable for storing as a golden snapshot.
+    ///
+    /// Everything which varies between runs of the same scenario is left out or
+    /// normalised: ids, timestamps and durations are omitted; UUIDs, hexadecimal
+    /// ids and ports within operation names and string values are replaced with
+    /// placeholders such as `{uuid}`; and volatile tags have their values redacted.
+    /// Every remaining tag and event is listed, sorted by key, beneath its span.
+    ///
+    /// ```text
+    /// POST /items [cart_server]
+    /// │     http.method = "POST"
+    /// └── GET /stock/{uuid} [cart_server] status=ERROR
+    ///       http.status_code = 500
+    ///       event: exception {exception.type = "io"}
+    /// ```
+    pub fn to_snapshot(&self, settings: &SnapshotSettings) -> String {
+        let redactor = Redactor::new();
+        let mut output = String::new();
+        write_subtree(&mut output, self.root(), "", "", settings, &redactor);
+        output
+    }
+}
+
+fn write_subtree(
+    output: &mut String,
+    span: TraceSpan,
+    first_line_prefix: &str,
+    continuation_prefix: &str,
+    settings: &SnapshotSettings,
+    redactor: &Redactor,
+) {
+    let children: Vec<_> = span.children().collect();
+
+    write!(
+        output,
+        "{}{}",
+        first_line_prefix,
+        redactor.redact(&span.operation_name)
+    )
+    .unwrap();
+    if let Some(service_name) = span.service_name() {
+        write!(output, " [{}]", service_name).unwrap();
+    }
+    let status = span.status();
+    match status.code {
+        SpanStatusCode::Unset => {}
+        SpanStatusCode::Ok => write!(output, " status=OK").unwrap(),
+        SpanStatusCode::Error => write!(output, " status=ERROR").unwrap(),
+    }
+    if let Some(description) = status.description {
+        write!(output, " ({})", redactor.redact(&description)).unwrap();
+    }
+    output.push('\n');
+
+    let detail_prefix = if children.is_empty() {
+        format!("{}      ", continuation_prefix)
+    } else {
+        format!("{}│     ", continuation_prefix)
+    };
+
+    let mut tags: Vec<_> = span
+        .tags
+        .iter()
+        .flatten()
+        .filter(|tag| !settings.omitted_tags.contains(&tag.key))
+        .collect();
+    tags.sort_by(|a, b| a.key.cmp(&b.key));
+    for tag in tags {
+        writeln!(
+            output,
+            "{}{} = {}",
+            detail_prefix,
+            tag.key,
+            tag_value(&tag.key, tag.owned_value().ok(), settings, redactor)
+        )
+        .unwrap();
+    }
+
+    for event in span.events() {
+        let fields: Vec<String> = event
+            .fields
+            .iter()
+            .filter(|field| field.key != "event")
+            .map(|field| {
+                format!(
+                    "{} = {}",
+                    field.key,
+                    tag_value(&field.key, field.owned_value().ok(), settings, redactor)
+                )
+            })
+            .collect();
+        write!(
+            output,
+            "{}event: {}",
+            detail_prefix,
+            redactor.redact(event.event_name().unwrap_or_default())
+        )
+        .unwrap();
+        if !fields.is_empty() {
+            write!(output, " {{{}}}", fields.join(", ")).unwrap();
+        }
+        output.push('\n');
+    }
+
+    for (position, child) in children.iter().enumerate() {
+        let is_last = position + 1 == children.len();
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        write_subtree(
+            output,
+            *child,
+            &format!("{}{}", continuation_prefix, branch),
+            &format!("{}{}", continuation_prefix, indent),
+            settings,
+            redactor,
+        );
+    }
+}
+
+fn tag_value(
+    key: &str,
+    value: Option<OwnedTagValue>,
+    settings: &SnapshotSettings,
+    redactor: &Redactor,
+) -> String {
+    if key.ends_with(".port") || settings.redacted_tags.iter().any(|k| k == key) {
+        return "{redacted}".to_owned();
+    }
+    match value {
+        Some(OwnedTagValue::String(value)) => format!("{:?}", redactor.redact(&value)),
+        Some(value) => value.to_string(),
+        None => "{invalid}".to_owned(),
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::jaeger_models::{SpanStatusCode, Trace, TraceSpan};
+
+/// Tags which are informative enough to be shown alongside each span when
+/// rendering a trace.
+const KEY_TAGS: &[&str] = &[
+    "span.kind",
+    "http.method",
+    "http.route",
+    "http.status_code",
+    "db.system",
+    "messaging.system",
+];
+
+/// Renders the trace as an indented tree, one span per line, showing each span's
+/// operation name, service, duration, status and key tags.
+///
+/// ```text
+/// POST /items [cart_server] 4.2ms
+/// └── add_item_to_cart [cart_server] 3.9ms
+///     └── GET /stock/1234 [cart_server] 3.1ms status=ERROR http.method=GET http.status_code=500
+/// ```
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_subtree(f, self.root(), "", "")
+    }
+}
+
+fn write_subtree(
+    f: &mut Formatter<'_>,
+    span: TraceSpan,
+    first_line_prefix: &str,
+    continuation_prefix: &str,
+) -> fmt::Result {
+    writeln!(f, "{}{}", first_line_prefix, SpanSummary(span))?;
+
+    let children: Vec<_> = span.children().collect();
+    for (position, child) in children.iter().enumerate() {
+        let is_last = position + 1 == children.len();
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        write_subtree(
+            f,
+            *child,
+            &format!("{}{}", continuation_prefix, branch),
+            &format!("{}{}", continuation_prefix, indent),
+        )?;
+    }
+    Ok(())
+}
+
+/// A single-line description of a span.
+pub(crate) struct SpanSummary<'t>(pub TraceSpan<'t>);
+
+impl<'t> Display for SpanSummary<'t> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let span = self.0;
+        write!(f, "{}", span.operation_name)?;
+        if let Some(service_name) = span.service_name() {
+            write!(f, " [{}]", service_name)?;
+        }
+        write!(f, " {}", format_duration(span.duration))?;
+
+        let status = span.status();
+        match status.code {
+            SpanStatusCode::Unset => {}
+            SpanStatusCode::Ok => write!(f, " status=OK")?,
+            SpanStatusCode::Error => write!(f, " status=ERROR")?,
+        }
+        if let Some(description) = status.description {
+            write!(f, " ({})", description)?;
+        }
+
+        for key in KEY_TAGS {
+            if let Some(value) = span.get_tag(key).and_then(|tag| tag.value().ok()) {
+                write!(f, " {}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format a duration in microseconds, as recorded on Jaeger spans.
+pub(crate) fn format_duration(micros: i64) -> String {
+    if micros < 0 {
+        format!("-{:?}", Duration::from_micros(micros.unsigned_abs()))
+    } else {
+        format!("{:?}", Duration::from_micros(micros as u64))
+    }
+}
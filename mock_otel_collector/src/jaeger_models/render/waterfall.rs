@@ -0,0 +1,113 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::format_duration;
+use crate::jaeger_models::Trace;
+
+const DEFAULT_WIDTH: usize = 60;
+
+/// A text timeline of a trace, showing when each span started relative to the
+/// root span and how long it lasted.
+///
+/// ```text
+/// POST /items [cart_server]           0µs     4.2ms |████████████████████████████████|
+///   add_item_to_cart [cart_server]  120µs     3.9ms |  ██████████████████████████████|
+///     GET /stock/1234 [cart_server] 300µs     3.1ms |    ███████████████████████     |
+/// ```
+pub struct Waterfall<'t> {
+    trace: &'t Trace,
+    width: usize,
+}
+
+impl Trace {
+    /// Render this trace as a [`Waterfall`].
+    pub fn waterfall(&self) -> Waterfall<'_> {
+        Waterfall {
+            trace: self,
+            width: DEFAULT_WIDTH,
+        }
+    }
+}
+
+impl<'t> Waterfall<'t> {
+    /// Set the number of characters used for the timeline bars.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+}
+
+impl<'t> Display for Waterfall<'t> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let trace_start = self.trace.root().start_time;
+        // Spans are not guaranteed to finish before the root does, so the timeline
+        // extends to whichever span finishes last.
+        let trace_end = self
+            .trace
+            .descendants()
+            .map(|span| span.start_time + span.duration.max(0))
+            .max()
+            .unwrap_or(trace_start);
+        let total = (trace_end - trace_start).max(1) as f64;
+
+        let rows: Vec<_> = self
+            .trace
+            .descendants()
+            .map(|span| {
+                let label = match span.service_name() {
+                    Some(service_name) => format!(
+                        "{}{} [{}]",
+                        "  ".repeat(span.depth()),
+                        span.operation_name,
+                        service_name
+                    ),
+                    None => format!("{}{}", "  ".repeat(span.depth()), span.operation_name),
+                };
+                let offset = span.start_time - trace_start;
+                (label, offset, span.duration)
+            })
+            .collect();
+
+        let label_width = rows
+            .iter()
+            .map(|(label, _, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let formatted_offsets: Vec<_> = rows
+            .iter()
+            .map(|(_, offset, _)| format_duration(*offset))
+            .collect();
+        let offset_width = formatted_offsets.iter().map(|o| o.chars().count()).max();
+        let formatted_durations: Vec<_> = rows
+            .iter()
+            .map(|(_, _, duration)| format_duration(*duration))
+            .collect();
+        let duration_width = formatted_durations.iter().map(|d| d.chars().count()).max();
+
+        for (((label, offset, duration), formatted_offset), formatted_duration) in rows
+            .iter()
+            .zip(formatted_offsets.iter())
+            .zip(formatted_durations.iter())
+        {
+            let width = self.width as f64;
+            let bar_start = ((*offset as f64 / total) * width).floor().max(0.0) as usize;
+            let bar_end = (((offset + duration.max(&0)) as f64 / total) * width).ceil() as usize;
+            let bar_start = bar_start.min(self.width - 1);
+            let bar_end = bar_end.clamp(bar_start + 1, self.width);
+
+            writeln!(
+                f,
+                "{:<label_width$} {:>offset_width$} {:>duration_width$} |{}{}{}|",
+                label,
+                formatted_offset,
+                formatted_duration,
+                " ".repeat(bar_start),
+                "█".repeat(bar_end - bar_start),
+                " ".repeat(self.width - bar_end),
+                label_width = label_width,
+                offset_width = offset_width.unwrap_or(0),
+                duration_width = duration_width.unwrap_or(0),
+            )?;
+        }
+        Ok(())
+    }
+}
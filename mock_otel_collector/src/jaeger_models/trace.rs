@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use anyhow::{anyhow, bail};
+
+use super::{Process, Span, SpanId, StringMatcher, TraceId};
+
+/// A trace assembled from a set of received spans.
+///
+/// Spans are held in an arena, ordered depth-first from the root, and linked to
+/// one another by index. Unlike a tree of reference-counted nodes, a `Trace` owns
+/// all of its data and is `Send + Sync`, so it can be freely moved into async
+/// blocks or across threads.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    nodes: Vec<TraceNode>,
+}
+
+#[derive(Clone, Debug)]
+struct TraceNode {
+    span: Span,
+    process: Option<Process>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    follows_from: Vec<usize>,
+    depth: usize,
+    /// One past the index of the last node in this node's subtree.
+    subtree_end: usize,
+}
+
+impl Trace {
+    /// Assemble a trace from its spans, without any knowledge of the processes
+    /// which reported them.
+    pub fn from_spans(spans: impl IntoIterator<Item = Span>) -> Result<Trace, anyhow::Error> {
+        Self::from_process_spans(spans.into_iter().map(|span| (span, None)))
+    }
+
+    /// Assemble a trace from its spans, each paired with the process that
+    /// reported it, if known.
+    ///
+    /// Parent/child relationships are resolved using [`Span::parent_id`]. The spans
+    /// must form a single tree: exactly one root, and no span whose parent is missing.
+    pub fn from_process_spans(
+        spans: impl IntoIterator<Item = (Span, Option<Process>)>,
+    ) -> Result<Trace, anyhow::Error> {
+        let spans: Vec<(Span, Option<Process>)> = spans.into_iter().collect();
+        if spans.is_empty() {
+            bail!("Traces must include at least one span.");
+        }
+
+        let mut positions_by_span_id = HashMap::new();
+        for (position, (span, _)) in spans.iter().enumerate() {
+            if positions_by_span_id.insert(span.id(), position).is_some() {
+                bail!("Multiple spans found with span id {}", span.id());
+            }
+        }
+
+        let mut roots = Vec::new();
+        let mut children_by_position: Vec<Vec<usize>> = vec![Vec::new(); spans.len()];
+        for (position, (span, _)) in spans.iter().enumerate() {
+            match span.parent_id() {
+                None => roots.push(position),
+                Some(parent_id) => {
+                    let parent_position = positions_by_span_id
+                        .get(&parent_id)
+                        .ok_or_else(|| anyhow!("Spans found with missing parents"))?;
+                    children_by_position[*parent_position].push(position);
+                }
+            }
+        }
+
+        let root = match roots.as_slice() {
+            [] => bail!("No root span found"),
+            [root] => *root,
+            _ => bail!("Multiple root spans found"),
+        };
+
+        for children in children_by_position.iter_mut() {
+            children
+                .sort_by_key(|&position| (spans[position].0.start_time, spans[position].0.span_id));
+        }
+
+        // Lay the spans out depth-first from the root, so that every subtree
+        // occupies a contiguous range of the arena.
+        let mut order = Vec::with_capacity(spans.len());
+        let mut stack = vec![root];
+        while let Some(position) = stack.pop() {
+            order.push(position);
+            stack.extend(children_by_position[position].iter().rev());
+        }
+
+        if order.len() != spans.len() {
+            bail!("Spans found with missing parents");
+        }
+
+        let mut indices_by_position = vec![0; spans.len()];
+        for (index, &position) in order.iter().enumerate() {
+            indices_by_position[position] = index;
+        }
+
+        let mut spans: Vec<Option<(Span, Option<Process>)>> = spans.into_iter().map(Some).collect();
+        let mut nodes: Vec<TraceNode> = order
+            .iter()
+            .map(|&position| {
+                let (span, process) = spans[position].take().unwrap();
+                TraceNode {
+                    span,
+                    process,
+                    parent: None,
+                    children: children_by_position[position]
+                        .iter()
+                        .map(|child| indices_by_position[*child])
+                        .collect(),
+                    follows_from: Vec::new(),
+                    depth: 0,
+                    subtree_end: 0,
+                }
+            })
+            .collect();
+
+        for index in 0..nodes.len() {
+            for child in nodes[index].children.clone() {
+                nodes[child].parent = Some(index);
+                nodes[child].depth = nodes[index].depth + 1;
+            }
+            nodes[index].follows_from = nodes[index]
+                .span
+                .follows_from()
+                .filter_map(|r| positions_by_span_id.get(&r.referenced_span_id()))
+                .map(|position| indices_by_position[*position])
+                .collect();
+        }
+
+        for index in (0..nodes.len()).rev() {
+            nodes[index].subtree_end = nodes[index]
+                .children
+                .last()
+                .map_or(index + 1, |last_child| nodes[*last_child].subtree_end);
+        }
+
+        Ok(Trace { nodes })
+    }
+
+    /// The root span of the trace.
+    pub fn root(&self) -> TraceSpan<'_> {
+        self.span_at(0)
+    }
+
+    /// The id shared by every span in the trace.
+    pub fn trace_id(&self) -> TraceId {
+        self.root().trace_id()
+    }
+
+    /// The number of spans in the trace.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the trace holds no spans. A successfully assembled trace always
+    /// has at least its root span.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every span in the trace, depth-first from (and including) the root.
+    pub fn descendants(&self) -> impl Iterator<Item = TraceSpan<'_>> {
+        (0..self.nodes.len()).map(move |index| self.span_at(index))
+    }
+
+    /// The direct children of `span`, ordered by start time.
+    pub fn children<'t>(&'t self, span: TraceSpan<'t>) -> impl Iterator<Item = TraceSpan<'t>> {
+        span.children()
+    }
+
+    /// Find the span with the given span id.
+    pub fn get_span(&self, span_id: SpanId) -> Option<TraceSpan<'_>> {
+        self.descendants().find(|s| s.id() == span_id)
+    }
+
+    /// Find the first span, depth-first, whose operation name matches
+    /// `operation_name`: either an exact name, or a [`StringMatcher`] such as a glob.
+    pub fn find_by_operation(
+        &self,
+        operation_name: impl Into<StringMatcher>,
+    ) -> Option<TraceSpan<'_>> {
+        let operation_name = operation_name.into();
+        self.descendants()
+            .find(|s| operation_name.matches(&s.operation_name))
+    }
+
+    /// Find every span, depth-first, whose operation name matches `operation_name`.
+    pub fn find_all_by_operation(
+        &self,
+        operation_name: impl Into<StringMatcher>,
+    ) -> impl Iterator<Item = TraceSpan<'_>> {
+        let operation_name = operation_name.into();
+        self.descendants()
+            .filter(move |s| operation_name.matches(&s.operation_name))
+    }
+
+    /// Find the first span, depth-first, satisfying `predicate`.
+    pub fn find(&self, predicate: impl Fn(&TraceSpan) -> bool) -> Option<TraceSpan<'_>> {
+        self.descendants().find(|s| predicate(s))
+    }
+
+    fn span_at(&self, index: usize) -> TraceSpan<'_> {
+        TraceSpan { trace: self, index }
+    }
+}
+
+/// A span within a [`Trace`], from which the rest of the trace can be navigated.
+///
+/// Dereferences to the underlying [`Span`].
+#[derive(Clone, Copy)]
+pub struct TraceSpan<'t> {
+    trace: &'t Trace,
+    index: usize,
+}
+
+impl<'t> TraceSpan<'t> {
+    /// The underlying span.
+    pub fn span(&self) -> &'t Span {
+        &self.node().span
+    }
+
+    /// The process which reported this span, if known.
+    pub fn process(&self) -> Option<&'t Process> {
+        self.node().process.as_ref()
+    }
+
+    /// The name of the service which reported this span, if known.
+    pub fn service_name(&self) -> Option<&'t str> {
+        self.process().map(|p| p.service_name.as_str())
+    }
+
+    /// The position of this span within the trace, depth-first from the root.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The number of ancestors this span has; zero for the root.
+    pub fn depth(&self) -> usize {
+        self.node().depth
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.node().parent.is_none()
+    }
+
+    pub fn parent(&self) -> Option<TraceSpan<'t>> {
+        self.node().parent.map(|index| self.trace.span_at(index))
+    }
+
+    /// The direct children of this span, ordered by start time.
+    pub fn children(&self) -> impl Iterator<Item = TraceSpan<'t>> {
+        let trace = self.trace;
+        self.node()
+            .children
+            .iter()
+            .map(move |index| trace.span_at(*index))
+    }
+
+    /// Every span below this one, depth-first, excluding this span itself.
+    pub fn descendants(&self) -> impl Iterator<Item = TraceSpan<'t>> {
+        let trace = self.trace;
+        (self.index + 1..self.node().subtree_end).map(move |index| trace.span_at(index))
+    }
+
+    /// Every span above this one, from its parent up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = TraceSpan<'t>> {
+        std::iter::successors(self.parent(), |span| span.parent())
+    }
+
+    /// The spans within the trace that this span has a `FOLLOWS_FROM` reference to.
+    /// See [`Span::follows_from`] for the references themselves.
+    pub fn follows_from_spans(&self) -> impl Iterator<Item = TraceSpan<'t>> {
+        let trace = self.trace;
+        self.node()
+            .follows_from
+            .iter()
+            .map(move |index| trace.span_at(*index))
+    }
+
+    /// The trace this span belongs to.
+    pub fn trace(&self) -> &'t Trace {
+        self.trace
+    }
+
+    fn node(&self) -> &'t TraceNode {
+        &self.trace.nodes[self.index]
+    }
+}
+
+impl<'t> Deref for TraceSpan<'t> {
+    type Target = Span;
+
+    fn deref(&self) -> &Span {
+        self.span()
+    }
+}
+
+impl<'t> std::fmt::Debug for TraceSpan<'t> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.span().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    fn operations<'t>(spans: impl Iterator<Item = TraceSpan<'t>>) -> Vec<&'t str> {
+        spans
+            .map(|span| span.span().operation_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn spans_are_laid_out_depth_first_with_children_in_start_order() {
+        let root = SpanBuilder::new("root").build();
+        let child = |operation: &str, parent: &Span, offset: u64| {
+            SpanBuilder::new(operation)
+                .child_of(parent)
+                .start_offset(Duration::from_micros(offset))
+                .build()
+        };
+        let late = child("late", &root, 20);
+        let early = child("early", &root, 10);
+        let nested = child("nested", &early, 5);
+
+        let trace = Trace::from_spans(vec![nested, late, root, early]).unwrap();
+
+        assert_eq!(
+            operations(trace.descendants()),
+            ["root", "early", "nested", "late"]
+        );
+        let early = trace.find_by_operation("early").unwrap();
+        assert_eq!(early.depth(), 1);
+        assert_eq!(operations(early.descendants()), ["nested"]);
+        assert_eq!(operations(early.children()), ["nested"]);
+        let nested = trace.find_by_operation("nested").unwrap();
+        assert_eq!(operations(nested.ancestors()), ["early", "root"]);
+    }
+
+    #[test]
+    fn follows_from_references_within_the_trace_are_resolved() {
+        let root = SpanBuilder::new("root").build();
+        let producer = SpanBuilder::new("produce").child_of(&root).build();
+        let consumer = SpanBuilder::new("consume")
+            .child_of(&root)
+            .follows_from(&producer)
+            .start_offset(Duration::from_micros(10))
+            .build();
+
+        let trace = Trace::from_spans(vec![root, producer, consumer]).unwrap();
+
+        let consumer = trace.find_by_operation("consume").unwrap();
+        assert_eq!(operations(consumer.follows_from_spans()), ["produce"]);
+        assert_eq!(consumer.follows_from().count(), 1);
+    }
+
+    #[test]
+    fn duplicate_span_ids_are_rejected() {
+        let root = SpanBuilder::new("root").build();
+        let duplicate = SpanBuilder::new("duplicate")
+            .trace_id(root.trace_id())
+            .span_id(root.id())
+            .build();
+
+        let error = Trace::from_spans(vec![root, duplicate]).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("Multiple spans found with span id"));
+    }
+
+    #[test]
+    fn missing_parents_are_rejected() {
+        let root = SpanBuilder::new("root").build();
+        let parent = SpanBuilder::new("never received").child_of(&root).build();
+        let orphan = SpanBuilder::new("orphan").child_of(&parent).build();
+
+        let error = Trace::from_spans(vec![root, orphan]).unwrap_err();
+
+        assert_eq!(error.to_string(), "Spans found with missing parents");
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let root = SpanBuilder::new("root").trace_id(TraceId(1)).build();
+        let first = SpanBuilder::new("first")
+            .trace_id(TraceId(1))
+            .span_id(SpanId(10))
+            .build();
+        let second = SpanBuilder::new("second").child_of(&first).build();
+        let first = SpanBuilder::new("first")
+            .span_id(SpanId(10))
+            .child_of(&second)
+            .build();
+
+        assert!(Trace::from_spans(vec![root, first, second]).is_err());
+    }
+
+    #[test]
+    fn traces_need_exactly_one_root() {
+        assert!(Trace::from_spans(Vec::new()).is_err());
+        let error = Trace::from_spans(vec![
+            SpanBuilder::new("a").trace_id(TraceId(1)).build(),
+            SpanBuilder::new("b").trace_id(TraceId(1)).build(),
+        ])
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Multiple root spans found");
+    }
+}
@@ -2,4 +2,10 @@
 
 pub mod jaeger_models;
 mod server;
+mod snapshot;
+mod span_metrics;
+mod span_store;
 pub use server::DetachedMockOtelCollector;
+pub use snapshot::{assert_snapshot, SnapshotOutcome};
+pub use span_metrics::{SpanMetricKey, SpanMetricSeries, SpanMetrics};
+pub use span_store::{ReceivedSpan, TraceCompletenessSettings};
@@ -1,58 +1,176 @@
-use std::io;
 use std::net::TcpListener;
+use std::path::Path as FilePath;
 use std::sync::{Arc, Mutex};
 use std::thread::{self};
+use std::time::Instant;
+use std::{fs, io};
 
 use actix_web::dev::Server;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::rt::time::sleep;
 use actix_web::rt::System;
-use actix_web::web::{get, post, BytesMut, Data, Payload};
-use actix_web::{App, HttpResponse, HttpServer, Responder};
-use anyhow::Context;
+use actix_web::web::{get, post, BytesMut, Data, Path, Payload};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, Responder};
+use anyhow::{anyhow, Context};
 use futures_util::StreamExt;
-use rctree::Node;
 use reqwest::ClientBuilder;
-use thrift::protocol::TBinaryInputProtocol;
 
-use crate::jaeger_models::span_tree::build_span_tree;
-use crate::jaeger_models::{Batch, Span};
+use crate::jaeger_models::{
+    AggregateTraceStats, Batch, DependencyGraph, Trace, TraceId, TraceStats,
+    THRIFT_BINARY_CONTENT_TYPE, THRIFT_COMPACT_CONTENT_TYPE,
+};
+use crate::span_metrics::SpanMetrics;
+use crate::span_store::{ReceivedSpan, SpanStore, TraceCompletenessSettings};
 
+/// Accepts batches encoded with either the Thrift binary protocol (the default,
+/// as sent by the OpenTelemetry Jaeger exporter) or, if the request says so in its
+/// `Content-Type`, the Thrift compact protocol.
 async fn post_traces_handler(
+    request: HttpRequest,
     payload: Payload,
-    received_batches: Data<Mutex<Vec<Batch>>>,
+    span_store: Data<Mutex<SpanStore>>,
 ) -> impl Responder {
     async fn handle(
+        request: HttpRequest,
         mut payload: Payload,
-        received_batches: Data<Mutex<Vec<Batch>>>,
+        span_store: Data<Mutex<SpanStore>>,
     ) -> Result<(), anyhow::Error> {
         let mut bytes = BytesMut::new();
         while let Some(item) = payload.next().await {
             bytes.extend_from_slice(&item?);
         }
 
-        let mut binary_input = TBinaryInputProtocol::new(bytes.as_ref(), false);
-        let batch = Batch::read_from_in_protocol(&mut binary_input)?;
-        let mut data = received_batches.lock().unwrap();
-        data.push(batch);
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let batch = match content_type {
+            Some(THRIFT_COMPACT_CONTENT_TYPE) => Batch::from_thrift_compact(bytes.as_ref())?,
+            _ => Batch::from_thrift_binary(bytes.as_ref())?,
+        };
+        let mut data = span_store.lock().unwrap();
+        data.ingest(batch);
         Ok(())
     }
 
-    match handle(payload, received_batches).await {
+    match handle(request, payload, span_store).await {
         Ok(_) => HttpResponse::Ok(),
         Err(_) => HttpResponse::InternalServerError(),
     }
 }
 
+/// Render a trace from the store as text, with `render`.
+fn render_trace(
+    trace_id: &str,
+    span_store: &Mutex<SpanStore>,
+    content_type: &str,
+    render: impl Fn(&Trace) -> String,
+) -> HttpResponse {
+    let trace_id = match trace_id.parse::<TraceId>() {
+        Ok(trace_id) => trace_id,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+
+    let trace = span_store.lock().unwrap().get_trace(trace_id);
+    match trace {
+        Ok(trace) => HttpResponse::Ok()
+            .content_type(content_type)
+            .body(render(&trace)),
+        Err(e) => HttpResponse::NotFound().body(e.to_string()),
+    }
+}
+
+async fn get_trace_waterfall_handler(
+    trace_id: Path<String>,
+    span_store: Data<Mutex<SpanStore>>,
+) -> impl Responder {
+    render_trace(
+        &trace_id,
+        &span_store,
+        "text/plain; charset=utf-8",
+        |trace| trace.waterfall().to_string(),
+    )
+}
+
+async fn get_trace_dot_handler(
+    trace_id: Path<String>,
+    span_store: Data<Mutex<SpanStore>>,
+) -> impl Responder {
+    render_trace(
+        &trace_id,
+        &span_store,
+        "text/vnd.graphviz; charset=utf-8",
+        |trace| trace.dot().to_string(),
+    )
+}
+
+async fn get_trace_mermaid_handler(
+    trace_id: Path<String>,
+    span_store: Data<Mutex<SpanStore>>,
+) -> impl Responder {
+    render_trace(
+        &trace_id,
+        &span_store,
+        "text/plain; charset=utf-8",
+        |trace| trace.mermaid().to_string(),
+    )
+}
+
+async fn get_trace_chrome_handler(
+    trace_id: Path<String>,
+    span_store: Data<Mutex<SpanStore>>,
+) -> impl Responder {
+    render_trace(&trace_id, &span_store, "application/json", |trace| {
+        trace.to_chrome_trace().to_string()
+    })
+}
+
+/// Serves the links between services seen in every received trace, in the
+/// format used by Jaeger's query API.
+async fn get_dependencies_handler(span_store: Data<Mutex<SpanStore>>) -> impl Responder {
+    let traces = span_store.lock().unwrap().traces();
+    let dependencies = DependencyGraph::from_traces(&traces);
+    let links: Vec<_> = dependencies.links().collect();
+    HttpResponse::Ok().json(serde_json::json!({ "data": links }))
+}
+
+/// Serves the span-derived metrics in the Prometheus text exposition format.
+async fn get_metrics_handler(span_store: Data<Mutex<SpanStore>>) -> impl Responder {
+    let metrics = span_store.lock().unwrap().metrics().to_prometheus();
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics)
+}
+
 fn run_server(
     listener: TcpListener,
-    batch_store: Arc<Mutex<Vec<Batch>>>,
+    span_store: Arc<Mutex<SpanStore>>,
 ) -> Result<Server, io::Error> {
-    let batch_store = Data::from(batch_store);
+    let span_store = Data::from(span_store);
 
     Ok(HttpServer::new(move || {
         App::new()
-            .app_data(batch_store.clone())
+            .app_data(span_store.clone())
             .route("/up", get().to(HttpResponse::Ok))
             .route("/api/traces", post().to(post_traces_handler))
+            .route(
+                "/api/traces/{trace_id}/waterfall",
+                get().to(get_trace_waterfall_handler),
+            )
+            .route(
+                "/api/traces/{trace_id}/chrome",
+                get().to(get_trace_chrome_handler),
+            )
+            .route(
+                "/api/traces/{trace_id}/dot",
+                get().to(get_trace_dot_handler),
+            )
+            .route(
+                "/api/traces/{trace_id}/mermaid",
+                get().to(get_trace_mermaid_handler),
+            )
+            .route("/api/dependencies", get().to(get_dependencies_handler))
+            .route("/metrics", get().to(get_metrics_handler))
     })
     .listen(listener)?
     .run())
@@ -60,7 +178,7 @@ fn run_server(
 
 pub struct DetachedMockOtelCollector {
     base_url: String,
-    batch_store: Arc<Mutex<Vec<Batch>>>,
+    span_store: Arc<Mutex<SpanStore>>,
 }
 
 impl DetachedMockOtelCollector {
@@ -75,13 +193,13 @@ impl DetachedMockOtelCollector {
         let listener =
             TcpListener::bind(address).with_context(|| format!("Failed to bind to {}", address))?;
 
-        let batch_store = Arc::new(Mutex::new(Vec::<Batch>::new()));
+        let span_store = Arc::new(Mutex::new(SpanStore::default()));
         let base_url = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
 
-        let thread_batch_store = batch_store.clone();
+        let thread_span_store = span_store.clone();
         thread::spawn(move || {
             System::new().block_on(async move {
-                run_server(listener, thread_batch_store)
+                run_server(listener, thread_span_store)
                     .expect("Failed to listen for incoming connections")
                     .await
                     .expect("Server failed unexpectedly");
@@ -91,7 +209,7 @@ impl DetachedMockOtelCollector {
 
         Ok(Self {
             base_url,
-            batch_store,
+            span_store,
         })
     }
 
@@ -111,19 +229,151 @@ impl DetachedMockOtelCollector {
         self.base_url.to_owned()
     }
 
-    /// Retrieve a trace, in the form of a [`rctree::Node<Span>`], from the in-memory
-    /// store of received [`Span`]s.
-    pub async fn get_trace(&self, trace_id: &str) -> Result<Node<Span>, anyhow::Error> {
-        let spans: Vec<Span> = {
-            let batches = self.batch_store.lock().unwrap();
-            batches
-                .iter()
-                .flat_map(|batch| batch.spans.iter())
-                .filter(|x| x.hex_trace_id() == trace_id)
-                .cloned()
-                .collect()
-        };
+    /// Send a batch of spans to the server, as an exporter would, encoded with
+    /// the Thrift binary protocol.
+    pub async fn post_batch(&self, batch: &Batch) -> Result<(), anyhow::Error> {
+        let reqwest_client = ClientBuilder::new()
+            .build()
+            .context("Failed to build reqwest client")?;
+
+        let url = format!("{}/api/traces", self.base_url());
+        let _ = reqwest_client
+            .post(url)
+            .header(CONTENT_TYPE.as_str(), THRIFT_BINARY_CONTENT_TYPE)
+            .body(batch.to_thrift_binary()?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Retrieve a [`Trace`] from the in-memory store of received spans.
+    pub async fn get_trace(&self, trace_id: impl Into<TraceId>) -> Result<Trace, anyhow::Error> {
+        let span_store = self.span_store.lock().unwrap();
+        span_store.get_trace(trace_id.into())
+    }
+
+    /// Wait until a trace is complete, as judged by the default
+    /// [`TraceCompletenessSettings`], and retrieve it. Asserting against a trace
+    /// while its spans are still arriving can otherwise pass or fail spuriously.
+    pub async fn wait_for_complete_trace(
+        &self,
+        trace_id: impl Into<TraceId>,
+    ) -> Result<Trace, anyhow::Error> {
+        self.wait_for_complete_trace_with(trace_id, &TraceCompletenessSettings::default())
+            .await
+    }
+
+    /// Wait until a trace is complete, as judged by `settings`, and retrieve it.
+    pub async fn wait_for_complete_trace_with(
+        &self,
+        trace_id: impl Into<TraceId>,
+        settings: &TraceCompletenessSettings,
+    ) -> Result<Trace, anyhow::Error> {
+        let trace_id = trace_id.into();
+        let deadline = Instant::now() + settings.timeout;
+        let mut assembly_error = None;
+        loop {
+            let span_count = {
+                let span_store = self.span_store.lock().unwrap();
+                match span_store.complete_trace(trace_id, settings) {
+                    Ok(Some(trace)) => return Ok(trace),
+                    Ok(None) => {}
+                    Err(error) => assembly_error = Some(error),
+                }
+                span_store.trace_spans(trace_id).count()
+            };
+
+            if Instant::now() >= deadline {
+                let message = format!(
+                    "Trace {} was not complete after {:?}; {} spans were received",
+                    trace_id, settings.timeout, span_count
+                );
+                return Err(match assembly_error {
+                    Some(error) => error.context(message),
+                    None => anyhow!(message),
+                });
+            }
+            sleep(settings.poll_interval).await;
+        }
+    }
+
+    /// Convert a trace to the Chrome trace event format, as described by
+    /// [`Trace::to_chrome_trace`].
+    pub async fn get_chrome_trace(
+        &self,
+        trace_id: impl Into<TraceId>,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        Ok(self.get_trace(trace_id).await?.to_chrome_trace())
+    }
+
+    /// Write a trace to a file in the Chrome trace event format, for example so
+    /// that the trace of a failing test can be opened in `ui.perfetto.dev`.
+    pub async fn write_chrome_trace(
+        &self,
+        trace_id: impl Into<TraceId>,
+        path: impl AsRef<FilePath>,
+    ) -> Result<(), anyhow::Error> {
+        let path = path.as_ref();
+        let chrome_trace = self.get_chrome_trace(trace_id).await?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .with_context(|| format!("Failed to create {}", directory.display()))?;
+        }
+        fs::write(path, serde_json::to_vec(&chrome_trace)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Compute [`TraceStats`] for a trace from the spans received so far.
+    pub async fn get_trace_stats(
+        &self,
+        trace_id: impl Into<TraceId>,
+    ) -> Result<TraceStats, anyhow::Error> {
+        Ok(self.get_trace(trace_id).await?.stats())
+    }
+
+    /// Aggregate statistics over every received trace satisfying `filter`, such
+    /// as those whose root span has a given operation name. Traces which cannot
+    /// yet be assembled, for example because a parent span has not arrived, are
+    /// left out.
+    pub async fn get_aggregate_stats(
+        &self,
+        filter: impl Fn(&Trace) -> bool,
+    ) -> AggregateTraceStats {
+        let traces = self.span_store.lock().unwrap().traces();
+        AggregateTraceStats::from_traces(traces.iter().filter(|trace| filter(trace)))
+    }
+
+    /// Compute the [`DependencyGraph`] of every received trace. As with
+    /// [`get_aggregate_stats`](Self::get_aggregate_stats), traces which cannot
+    /// yet be assembled are left out.
+    pub async fn get_dependencies(&self) -> DependencyGraph {
+        let traces = self.span_store.lock().unwrap().traces();
+        DependencyGraph::from_traces(&traces)
+    }
+
+    /// Retrieve the request counts, error counts and duration histograms
+    /// derived from every span received so far.
+    pub async fn get_span_metrics(&self) -> SpanMetrics {
+        let span_store = self.span_store.lock().unwrap();
+        span_store.metrics().clone()
+    }
+
+    /// Retrieve every distinct span received for a trace, along with how many
+    /// times each one was delivered.
+    pub async fn get_received_spans(&self, trace_id: impl Into<TraceId>) -> Vec<ReceivedSpan> {
+        let span_store = self.span_store.lock().unwrap();
+        span_store.trace_spans(trace_id.into()).cloned().collect()
+    }
 
-        build_span_tree(spans)
+    /// Retrieve the spans of a trace that were delivered more than once, for
+    /// example because an exporter retried a batch that had in fact arrived.
+    pub async fn get_duplicated_spans(&self, trace_id: impl Into<TraceId>) -> Vec<ReceivedSpan> {
+        let span_store = self.span_store.lock().unwrap();
+        span_store
+            .trace_spans(trace_id.into())
+            .filter(|received| received.receive_count > 1)
+            .cloned()
+            .collect()
     }
 }
@@ -0,0 +1,218 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+
+/// How [`assert_snapshot`] passed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    /// The snapshot matched.
+    Matched,
+    /// `UPDATE_SNAPSHOTS` was set, so the snapshot at this path was written.
+    Updated(PathBuf),
+}
+
+impl Display for SnapshotOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotOutcome::Matched => write!(f, "Snapshot matched"),
+            SnapshotOutcome::Updated(path) => write!(f, "Updated snapshot {}", path.display()),
+        }
+    }
+}
+
+/// Compare `actual` against the golden snapshot stored at `path`.
+///
+/// - If the snapshot matches, any pending snapshot left by an earlier failure is removed.
+/// - If it differs, or does not exist yet, `actual` is written alongside the snapshot
+///   with a `.new` suffix for review, and the assertion fails.
+///
+/// Setting the `UPDATE_SNAPSHOTS` environment variable is the only way to accept
+/// `actual` as the snapshot, whether it is new or changed.
+pub fn assert_snapshot(
+    path: impl AsRef<Path>,
+    actual: &str,
+) -> Result<SnapshotOutcome, anyhow::Error> {
+    check_snapshot(
+        path.as_ref(),
+        actual,
+        std::env::var_os("UPDATE_SNAPSHOTS").is_some(),
+    )
+}
+
+fn check_snapshot(
+    path: &Path,
+    actual: &str,
+    update: bool,
+) -> Result<SnapshotOutcome, anyhow::Error> {
+    let pending_path = path.with_extension("snap.new");
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+    }
+
+    let write = |target: &Path| {
+        fs::write(target, actual).with_context(|| format!("Failed to write {}", target.display()))
+    };
+    let remove_pending = || {
+        if pending_path.exists() {
+            fs::remove_file(&pending_path)
+                .with_context(|| format!("Failed to remove {}", pending_path.display()))?;
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    if update {
+        write(path)?;
+        remove_pending()?;
+        return Ok(SnapshotOutcome::Updated(path.to_owned()));
+    }
+
+    if !path.exists() {
+        write(&pending_path)?;
+        bail!(
+            "No snapshot exists at {}. The new snapshot was written to {} for review; \
+             rerun with UPDATE_SNAPSHOTS=1 to accept it.\n{}",
+            path.display(),
+            pending_path.display(),
+            actual
+        );
+    }
+
+    let expected = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .replace("\r\n", "\n");
+    if expected == actual {
+        remove_pending()?;
+        return Ok(SnapshotOutcome::Matched);
+    }
+
+    write(&pending_path)?;
+    bail!(
+        "Snapshot {} does not match. The new snapshot was written to {} for review; \
+         rerun with UPDATE_SNAPSHOTS=1 to accept the changes.\n{}",
+        path.display(),
+        pending_path.display(),
+        line_diff(&expected, actual)
+    )
+}
+
+/// A unified-style diff of two texts, line by line, with `-` marking lines only
+/// in `expected` and `+` marking lines only in `actual`.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // lengths[i][j] holds the length of the longest common subsequence of
+    // expected[i..] and actual[j..].
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len()
+            && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            diff.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn line_diffs_mark_removed_and_added_lines() {
+        let diff = line_diff("root\n  a\n  b\n", "root\n  b\n  c\n");
+
+        assert_eq!(diff, "  root\n-   a\n    b\n+   c\n");
+    }
+
+    /// A fresh directory for each test, so that tests running concurrently, or
+    /// left behind by an earlier run, never share snapshots.
+    fn temp_directory() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let directory = std::env::temp_dir().join(format!(
+            "snapshot-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        if directory.exists() {
+            fs::remove_dir_all(&directory).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn changed_snapshots_are_written_alongside_for_review() {
+        let directory = temp_directory();
+        let path = directory.join("trace.snap");
+        let pending_path = path.with_extension("snap.new");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&path, "root\n  a\n").unwrap();
+
+        let error = check_snapshot(&path, "root\n  b\n", false).unwrap_err();
+        assert!(error.to_string().contains("-   a\n+   b"));
+        assert_eq!(fs::read_to_string(&pending_path).unwrap(), "root\n  b\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "root\n  a\n");
+
+        assert_eq!(
+            check_snapshot(&path, "root\n  a\n", false).unwrap(),
+            SnapshotOutcome::Matched
+        );
+        assert!(!pending_path.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn missing_snapshots_fail_until_accepted() {
+        let directory = temp_directory();
+        let path = directory.join("trace.snap");
+        let pending_path = path.with_extension("snap.new");
+
+        let error = check_snapshot(&path, "root\n", false).unwrap_err();
+        assert!(error.to_string().starts_with("No snapshot exists"));
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&pending_path).unwrap(), "root\n");
+
+        assert!(check_snapshot(&path, "root\n", false).is_err());
+
+        assert_eq!(
+            check_snapshot(&path, "root\n", true).unwrap(),
+            SnapshotOutcome::Updated(path.clone())
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "root\n");
+        assert!(!pending_path.exists());
+
+        assert_eq!(
+            check_snapshot(&path, "root\n", false).unwrap(),
+            SnapshotOutcome::Matched
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
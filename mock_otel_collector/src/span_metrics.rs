@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::jaeger_models::{Process, Span, SpanStatusCode};
+
+/// Upper bounds, in seconds, of the buckets of the span duration histogram.
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Identifies a series of span metrics.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpanMetricKey {
+    pub service_name: String,
+    pub operation_name: String,
+    pub status: SpanStatusCode,
+}
+
+/// Request count and duration histogram for a single series.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanMetricSeries {
+    pub count: u64,
+    pub duration_sum: Duration,
+    /// The number of spans falling into each of the histogram's buckets, with a
+    /// final bucket for spans longer than the largest bound. Unlike Prometheus
+    /// buckets, these counts are not cumulative.
+    pub bucket_counts: Vec<u64>,
+}
+
+impl Default for SpanMetricSeries {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            duration_sum: Duration::ZERO,
+            bucket_counts: vec![0; DURATION_BUCKETS.len() + 1],
+        }
+    }
+}
+
+/// Rate, errors and duration ("RED") metrics derived from received spans, so
+/// that a service's own metrics can be checked against what its traces say.
+///
+/// Each distinct span is counted once, however many times it was delivered.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpanMetrics {
+    series: BTreeMap<SpanMetricKey, SpanMetricSeries>,
+}
+
+impl SpanMetrics {
+    pub(crate) fn record(&mut self, span: &Span, process: &Process) {
+        let key = SpanMetricKey {
+            service_name: process.service_name.clone(),
+            operation_name: span.operation_name.clone(),
+            status: span.status().code,
+        };
+        let duration = Duration::from_micros(span.duration.max(0) as u64);
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|bound| duration.as_secs_f64() <= *bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+
+        let series = self.series.entry(key).or_default();
+        series.count += 1;
+        series.duration_sum += duration;
+        series.bucket_counts[bucket] += 1;
+    }
+
+    /// Every series, ordered by service, operation and status.
+    pub fn series(&self) -> impl Iterator<Item = (&SpanMetricKey, &SpanMetricSeries)> {
+        self.series.iter()
+    }
+
+    /// The number of spans received for an operation, whatever their status.
+    pub fn request_count(&self, service_name: &str, operation_name: &str) -> u64 {
+        self.matching(service_name, operation_name, |_| true)
+    }
+
+    /// The number of spans received for an operation with an error status.
+    pub fn error_count(&self, service_name: &str, operation_name: &str) -> u64 {
+        self.matching(service_name, operation_name, |status| {
+            status == SpanStatusCode::Error
+        })
+    }
+
+    fn matching(
+        &self,
+        service_name: &str,
+        operation_name: &str,
+        status: impl Fn(SpanStatusCode) -> bool,
+    ) -> u64 {
+        self.series
+            .iter()
+            .filter(|(key, _)| {
+                key.service_name == service_name
+                    && key.operation_name == operation_name
+                    && status(key.status)
+            })
+            .map(|(_, series)| series.count)
+            .sum()
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut output = String::new();
+
+        header(
+            &mut output,
+            "span_calls_total",
+            "counter",
+            "Spans received, by service, operation and status.",
+        );
+        for (key, series) in &self.series {
+            writeln!(
+                output,
+                "span_calls_total{{{}}} {}",
+                labels(key),
+                series.count
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut output,
+            "span_errors_total",
+            "counter",
+            "Spans received with an error status, by service and operation.",
+        );
+        let mut errors: BTreeMap<(&str, &str), u64> = BTreeMap::new();
+        for (key, series) in &self.series {
+            let count = errors
+                .entry((&key.service_name, &key.operation_name))
+                .or_default();
+            if key.status == SpanStatusCode::Error {
+                *count += series.count;
+            }
+        }
+        for ((service_name, operation_name), count) in errors {
+            writeln!(
+                output,
+                "span_errors_total{{service_name=\"{}\",operation=\"{}\"}} {}",
+                escape(service_name),
+                escape(operation_name),
+                count
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut output,
+            "span_duration_seconds",
+            "histogram",
+            "Durations of received spans, by service, operation and status.",
+        );
+        for (key, series) in &self.series {
+            let labels = labels(key);
+            let mut cumulative = 0;
+            for (bound, count) in DURATION_BUCKETS.iter().zip(&series.bucket_counts) {
+                cumulative += count;
+                writeln!(
+                    output,
+                    "span_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                )
+                .unwrap();
+            }
+            writeln!(
+                output,
+                "span_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, series.count
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "span_duration_seconds_sum{{{}}} {}",
+                labels,
+                series.duration_sum.as_secs_f64()
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "span_duration_seconds_count{{{}}} {}",
+                labels, series.count
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
+
+fn header(output: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(output, "# HELP {} {}", name, help).unwrap();
+    writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn labels(key: &SpanMetricKey) -> String {
+    let status = match key.status {
+        SpanStatusCode::Unset => "UNSET",
+        SpanStatusCode::Ok => "OK",
+        SpanStatusCode::Error => "ERROR",
+    };
+    format!(
+        "service_name=\"{}\",operation=\"{}\",status_code=\"{}\"",
+        escape(&key.service_name),
+        escape(&key.operation_name),
+        status
+    )
+}
+
+/// Escape a label value as required by the Prometheus text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::SpanBuilder;
+
+    fn record(metrics: &mut SpanMetrics, span: SpanBuilder, duration: Duration) {
+        let process = Process::new("cart".to_owned(), None);
+        metrics.record(&span.duration(duration).build(), &process);
+    }
+
+    #[test]
+    fn spans_fall_into_the_first_bucket_which_holds_them() {
+        let mut metrics = SpanMetrics::default();
+        for millis in [0, 1, 2, 5, 10_000, 10_001] {
+            record(
+                &mut metrics,
+                SpanBuilder::new("checkout"),
+                Duration::from_millis(millis),
+            );
+        }
+
+        let (key, series) = metrics.series().next().unwrap();
+        assert_eq!(key.status, SpanStatusCode::Unset);
+        assert_eq!(series.count, 6);
+        assert_eq!(
+            series.bucket_counts,
+            vec![2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1]
+        );
+        assert_eq!(series.duration_sum, Duration::from_millis(20_009));
+    }
+
+    #[test]
+    fn errors_are_counted_per_operation_across_statuses() {
+        let mut metrics = SpanMetrics::default();
+        let millis = Duration::from_millis(1);
+        record(&mut metrics, SpanBuilder::new("checkout"), millis);
+        record(
+            &mut metrics,
+            SpanBuilder::new("checkout").error("failed"),
+            millis,
+        );
+        record(
+            &mut metrics,
+            SpanBuilder::new("checkout").error("failed"),
+            millis,
+        );
+        record(
+            &mut metrics,
+            SpanBuilder::new("checkout").tag("otel.status_code", "OK"),
+            millis,
+        );
+        record(&mut metrics, SpanBuilder::new("get cart"), millis);
+
+        assert_eq!(metrics.series().count(), 4);
+        assert_eq!(metrics.request_count("cart", "checkout"), 4);
+        assert_eq!(metrics.error_count("cart", "checkout"), 2);
+        assert_eq!(metrics.request_count("cart", "get cart"), 1);
+        assert_eq!(metrics.error_count("cart", "get cart"), 0);
+        assert_eq!(metrics.request_count("stock", "checkout"), 0);
+
+        let prometheus = metrics.to_prometheus();
+        assert!(prometheus
+            .contains("span_errors_total{service_name=\"cart\",operation=\"checkout\"} 2\n"));
+        assert!(prometheus
+            .contains("span_errors_total{service_name=\"cart\",operation=\"get cart\"} 0\n"));
+    }
+
+    #[test]
+    fn series_are_rendered_with_cumulative_buckets() {
+        let mut metrics = SpanMetrics::default();
+        for millis in [3, 20, 20, 30_000] {
+            record(
+                &mut metrics,
+                SpanBuilder::new("checkout"),
+                Duration::from_millis(millis),
+            );
+        }
+
+        let labels = "service_name=\"cart\",operation=\"checkout\",status_code=\"UNSET\"";
+        let expected = format!(
+            "# HELP span_calls_total Spans received, by service, operation and status.\n\
+             # TYPE span_calls_total counter\n\
+             span_calls_total{{{labels}}} 4\n\
+             # HELP span_errors_total Spans received with an error status, by service and operation.\n\
+             # TYPE span_errors_total counter\n\
+             span_errors_total{{service_name=\"cart\",operation=\"checkout\"}} 0\n\
+             # HELP span_duration_seconds Durations of received spans, by service, operation and status.\n\
+             # TYPE span_duration_seconds histogram\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.001\"}} 0\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.005\"}} 1\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.01\"}} 1\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.025\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.05\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.1\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.25\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"0.5\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"1\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"2.5\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"5\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"10\"}} 3\n\
+             span_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 4\n\
+             span_duration_seconds_sum{{{labels}}} 30.043\n\
+             span_duration_seconds_count{{{labels}}} 4\n",
+            labels = labels
+        );
+        assert_eq!(metrics.to_prometheus(), expected);
+    }
+}
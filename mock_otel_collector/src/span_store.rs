@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::jaeger_models::{Batch, Process, Span, SpanId, Trace, TraceId};
+use crate::span_metrics::SpanMetrics;
+
+/// A span held by the collector, along with the process that reported it and
+/// the number of times it has been delivered.
+#[derive(Clone, Debug)]
+pub struct ReceivedSpan {
+    pub span: Span,
+    pub process: Process,
+    pub receive_count: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct SpanKey {
+    trace_id: TraceId,
+    span_id: SpanId,
+}
+
+impl From<&Span> for SpanKey {
+    fn from(span: &Span) -> Self {
+        Self {
+            trace_id: span.trace_id(),
+            span_id: span.id(),
+        }
+    }
+}
+
+/// Settings controlling when a trace is considered complete, so that assertions
+/// are not made against a trace whose spans are still being exported.
+///
+/// A trace is complete once its root span has arrived, every span received can be
+/// placed in the tree, and either no new spans have arrived for `quiet_period` or
+/// `expected_span_count` spans have been received.
+#[derive(Clone, Debug)]
+pub struct TraceCompletenessSettings {
+    pub quiet_period: Duration,
+    pub expected_span_count: Option<usize>,
+    /// How long to wait for the trace to become complete before giving up.
+    pub timeout: Duration,
+    /// How often to check whether the trace has become complete.
+    pub poll_interval: Duration,
+}
+
+impl Default for TraceCompletenessSettings {
+    fn default() -> Self {
+        Self {
+            quiet_period: Duration::from_millis(250),
+            expected_span_count: None,
+            timeout: Duration::from_secs(5),
+            poll_interval: Duration::from_millis(50),
+        }
+    }
+}
+
+/// In-memory store of received spans, deduplicated by (trace id, span id).
+///
+/// Exporters may resend a batch if a previous attempt timed out, even if that
+/// attempt was in fact received. Rather than storing such spans twice, the
+/// store keeps the first copy and counts subsequent deliveries. Span metrics are
+/// likewise only updated when a span is first received.
+#[derive(Default)]
+pub(crate) struct SpanStore {
+    spans: Vec<ReceivedSpan>,
+    index: HashMap<SpanKey, usize>,
+    /// The positions in `spans` of each trace's spans, in the order they arrived.
+    trace_index: HashMap<TraceId, Vec<usize>>,
+    /// Every trace id, in the order each trace's first span arrived.
+    trace_ids: Vec<TraceId>,
+    metrics: SpanMetrics,
+    /// When a new span was last received for each trace.
+    last_received: HashMap<TraceId, Instant>,
+}
+
+impl SpanStore {
+    pub fn ingest(&mut self, batch: Batch) {
+        let process = batch.process;
+        for span in batch.spans {
+            let key = SpanKey::from(&span);
+            match self.index.get(&key) {
+                Some(&position) => self.spans[position].receive_count += 1,
+                None => {
+                    let positions = self.trace_index.entry(key.trace_id).or_default();
+                    if positions.is_empty() {
+                        self.trace_ids.push(key.trace_id);
+                    }
+                    positions.push(self.spans.len());
+                    self.index.insert(key, self.spans.len());
+                    self.metrics.record(&span, &process);
+                    self.last_received.insert(key.trace_id, Instant::now());
+                    self.spans.push(ReceivedSpan {
+                        span,
+                        process: process.clone(),
+                        receive_count: 1,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> &SpanMetrics {
+        &self.metrics
+    }
+
+    /// All spans received for the given trace, in the order they first arrived.
+    pub fn trace_spans(&self, trace_id: TraceId) -> impl Iterator<Item = &ReceivedSpan> {
+        self.trace_index
+            .get(&trace_id)
+            .into_iter()
+            .flatten()
+            .map(move |&position| &self.spans[position])
+    }
+
+    /// Assemble the spans received so far for the given trace into a [`Trace`].
+    pub fn get_trace(&self, trace_id: TraceId) -> Result<Trace, anyhow::Error> {
+        Trace::from_process_spans(
+            self.trace_spans(trace_id)
+                .map(|received| (received.span.clone(), Some(received.process.clone()))),
+        )
+    }
+
+    /// Every trace which can be assembled from the spans received so far, in the
+    /// order each trace's first span arrived. Traces which cannot, for example
+    /// because a parent span has not arrived, are left out.
+    pub fn traces(&self) -> Vec<Trace> {
+        self.trace_ids
+            .iter()
+            .filter_map(|&trace_id| self.get_trace(trace_id).ok())
+            .collect()
+    }
+
+    /// The trace, if it is complete according to `settings`. Once no more spans
+    /// are expected, an error is returned if the spans received cannot be
+    /// assembled into a trace, for example because a parent span is missing.
+    pub fn complete_trace(
+        &self,
+        trace_id: TraceId,
+        settings: &TraceCompletenessSettings,
+    ) -> Result<Option<Trace>, anyhow::Error> {
+        let span_count = self.trace_spans(trace_id).count();
+        let expected_count_reached = settings
+            .expected_span_count
+            .map_or(false, |expected| span_count >= expected);
+        let quiet = self
+            .last_received
+            .get(&trace_id)
+            .map_or(false, |last| last.elapsed() >= settings.quiet_period);
+
+        if expected_count_reached || quiet {
+            self.get_trace(trace_id).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jaeger_models::{BatchBuilder, SpanBuilder};
+
+    #[test]
+    fn traces_are_assembled_in_arrival_order_without_duplicates() {
+        let first_root = SpanBuilder::new("first").build();
+        let second_root = SpanBuilder::new("second").build();
+        let first_child = SpanBuilder::new("child").child_of(&first_root).build();
+        let orphan = SpanBuilder::new("orphan")
+            .child_of(&SpanBuilder::new("missing").build())
+            .build();
+
+        let mut store = SpanStore::default();
+        store.ingest(BatchBuilder::new("svc").span(first_root.clone()).build());
+        store.ingest(
+            BatchBuilder::new("svc")
+                .spans(vec![second_root, first_child.clone(), orphan])
+                .build(),
+        );
+        store.ingest(BatchBuilder::new("svc").span(first_child).build());
+
+        let roots: Vec<String> = store
+            .traces()
+            .iter()
+            .map(|trace| trace.root().operation_name.clone())
+            .collect();
+        assert_eq!(roots, ["first", "second"]);
+
+        let receive_counts: Vec<usize> = store
+            .trace_spans(first_root.trace_id())
+            .map(|received| received.receive_count)
+            .collect();
+        assert_eq!(receive_counts, [1, 2]);
+    }
+
+    #[test]
+    fn complete_traces_report_why_they_cannot_be_assembled() {
+        let root = SpanBuilder::new("root").build();
+        let child = SpanBuilder::new("child").child_of(&root).build();
+        let mut store = SpanStore::default();
+        store.ingest(BatchBuilder::new("svc").span(child.clone()).build());
+
+        let waiting = TraceCompletenessSettings {
+            quiet_period: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert!(store
+            .complete_trace(child.trace_id(), &waiting)
+            .unwrap()
+            .is_none());
+
+        let expecting_one = TraceCompletenessSettings {
+            expected_span_count: Some(1),
+            ..waiting
+        };
+        assert!(store
+            .complete_trace(child.trace_id(), &expecting_one)
+            .is_err());
+
+        store.ingest(BatchBuilder::new("svc").span(root).build());
+        let trace = store
+            .complete_trace(child.trace_id(), &expecting_one)
+            .unwrap()
+            .unwrap();
+        assert_eq!(trace.len(), 2);
+    }
+}